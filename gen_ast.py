@@ -12,6 +12,12 @@ ast = {
         "Set: Box<Expr> object, Token name, Box<Expr> value",
         "This: Token keyword",
         "SuperExpr: Token keyword, Token method",
+        "Stringify: Token token, Box<Expr> expression",
+        "Conditional: Box<Expr> condition, Box<Expr> then_branch, Box<Expr> else_branch",
+        "CompoundSet: Box<Expr> object, Token name, Token operator, Box<Expr> value",
+        "Postfix: Box<Expr> target, Token operator",
+        "Index: Box<Expr> object, Token bracket, Box<Expr> index",
+        "SetIndex: Box<Expr> object, Token bracket, Box<Expr> index, Box<Expr> value",
     ],
     "Stmt": [
         "Block: Vec<Stmt> statements",
@@ -20,9 +26,12 @@ ast = {
         "Var: Token name, Option<Box<Expr>> initializer",
         "IfStmt: Box<Expr> condition, Box<Stmt> then_branch, Option<Box<Stmt>> else_branch",
         "WhileStmt: Box<Expr> condition, Box<Stmt> body",
-        "Function: Token name, Vec<Token> params, Vec<Stmt> body",
+        "Function: Token name, Vec<Token> params, Vec<Stmt> body, bool is_getter",
         "ReturnStmt: Token keyword, Option<Box<Expr>> value",
-        "Class: Token name, Option<Box<Expr>> superclass, Vec<Function> methods",
+        "Class: Token name, Option<Box<Expr>> superclass, Vec<Expr> traits, Vec<Function> methods, Vec<Function> class_methods",
+        "TraitStmt: Token name, Vec<Function> methods, Vec<Function> class_methods",
+        "ThrowStmt: Token keyword, Box<Expr> value",
+        "TryStmt: Vec<Stmt> try_block, Option<Token> catch_name, Option<Vec<Stmt>> catch_block, Option<Vec<Stmt>> finally_block",
     ]
 }
 
@@ -72,7 +81,7 @@ for base in ast.keys():
         add_line("\t\tSelf {")
         for field in fields:
             field_name = field.split()[1]
-            add_line(f"\t\t\t{field_name}: {field_name},")
+            add_line(f"\t\t\t{field_name},")
         add_line("\t\t}")
         add_line("\t}")
         add_line("}")
@@ -14,6 +14,12 @@ pub enum Expr {
     Set(Set),
     This(This),
     SuperExpr(SuperExpr),
+    Stringify(Stringify),
+    Conditional(Conditional),
+    CompoundSet(CompoundSet),
+    Postfix(Postfix),
+    Index(Index),
+    SetIndex(SetIndex),
 }
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
@@ -25,8 +31,8 @@ pub struct Unary {
 impl Unary {
     pub fn new(operator: Token, right: Box<Expr>) -> Self {
         Self {
-            operator: operator,
-            right: right,
+            operator,
+            right,
         }
     }
 }
@@ -41,9 +47,9 @@ pub struct Binary {
 impl Binary {
     pub fn new(left: Box<Expr>, operator: Token, right: Box<Expr>) -> Self {
         Self {
-            left: left,
-            operator: operator,
-            right: right,
+            left,
+            operator,
+            right,
         }
     }
 }
@@ -56,7 +62,7 @@ pub struct Literal {
 impl Literal {
     pub fn new(value: Token) -> Self {
         Self {
-            value: value,
+            value,
         }
     }
 }
@@ -69,7 +75,7 @@ pub struct Grouping {
 impl Grouping {
     pub fn new(expression: Box<Expr>) -> Self {
         Self {
-            expression: expression,
+            expression,
         }
     }
 }
@@ -82,7 +88,7 @@ pub struct Variable {
 impl Variable {
     pub fn new(name: Token) -> Self {
         Self {
-            name: name,
+            name,
         }
     }
 }
@@ -96,8 +102,8 @@ pub struct Assign {
 impl Assign {
     pub fn new(name: Token, value: Box<Expr>) -> Self {
         Self {
-            name: name,
-            value: value,
+            name,
+            value,
         }
     }
 }
@@ -112,9 +118,9 @@ pub struct Logical {
 impl Logical {
     pub fn new(left: Box<Expr>, operator: Token, right: Box<Expr>) -> Self {
         Self {
-            left: left,
-            operator: operator,
-            right: right,
+            left,
+            operator,
+            right,
         }
     }
 }
@@ -129,9 +135,9 @@ pub struct Call {
 impl Call {
     pub fn new(callee: Box<Expr>, paren: Token, arguments: Vec<Expr>) -> Self {
         Self {
-            callee: callee,
-            paren: paren,
-            arguments: arguments,
+            callee,
+            paren,
+            arguments,
         }
     }
 }
@@ -145,8 +151,8 @@ pub struct Get {
 impl Get {
     pub fn new(object: Box<Expr>, name: Token) -> Self {
         Self {
-            object: object,
-            name: name,
+            object,
+            name,
         }
     }
 }
@@ -161,9 +167,9 @@ pub struct Set {
 impl Set {
     pub fn new(object: Box<Expr>, name: Token, value: Box<Expr>) -> Self {
         Self {
-            object: object,
-            name: name,
-            value: value,
+            object,
+            name,
+            value,
         }
     }
 }
@@ -176,7 +182,7 @@ pub struct This {
 impl This {
     pub fn new(keyword: Token) -> Self {
         Self {
-            keyword: keyword,
+            keyword,
         }
     }
 }
@@ -190,8 +196,110 @@ pub struct SuperExpr {
 impl SuperExpr {
     pub fn new(keyword: Token, method: Token) -> Self {
         Self {
-            keyword: keyword,
-            method: method,
+            keyword,
+            method,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct Stringify {
+    pub token: Token,
+    pub expression: Box<Expr>,
+}
+
+impl Stringify {
+    pub fn new(token: Token, expression: Box<Expr>) -> Self {
+        Self {
+            token,
+            expression,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct Conditional {
+    pub condition: Box<Expr>,
+    pub then_branch: Box<Expr>,
+    pub else_branch: Box<Expr>,
+}
+
+impl Conditional {
+    pub fn new(condition: Box<Expr>, then_branch: Box<Expr>, else_branch: Box<Expr>) -> Self {
+        Self {
+            condition,
+            then_branch,
+            else_branch,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct CompoundSet {
+    pub object: Box<Expr>,
+    pub name: Token,
+    pub operator: Token,
+    pub value: Box<Expr>,
+}
+
+impl CompoundSet {
+    pub fn new(object: Box<Expr>, name: Token, operator: Token, value: Box<Expr>) -> Self {
+        Self {
+            object,
+            name,
+            operator,
+            value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct Postfix {
+    pub target: Box<Expr>,
+    pub operator: Token,
+}
+
+impl Postfix {
+    pub fn new(target: Box<Expr>, operator: Token) -> Self {
+        Self {
+            target,
+            operator,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct Index {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+}
+
+impl Index {
+    pub fn new(object: Box<Expr>, bracket: Token, index: Box<Expr>) -> Self {
+        Self {
+            object,
+            bracket,
+            index,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct SetIndex {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+    pub value: Box<Expr>,
+}
+
+impl SetIndex {
+    pub fn new(object: Box<Expr>, bracket: Token, index: Box<Expr>, value: Box<Expr>) -> Self {
+        Self {
+            object,
+            bracket,
+            index,
+            value,
         }
     }
 }
@@ -211,6 +319,12 @@ pub trait ExprVisitor {
     fn visit_set(&mut self, set: &Set) -> Self::Result;
     fn visit_this(&mut self, this: &This) -> Self::Result;
     fn visit_superexpr(&mut self, superexpr: &SuperExpr) -> Self::Result;
+    fn visit_stringify(&mut self, stringify: &Stringify) -> Self::Result;
+    fn visit_conditional(&mut self, conditional: &Conditional) -> Self::Result;
+    fn visit_compoundset(&mut self, compoundset: &CompoundSet) -> Self::Result;
+    fn visit_postfix(&mut self, postfix: &Postfix) -> Self::Result;
+    fn visit_index(&mut self, index: &Index) -> Self::Result;
+    fn visit_setindex(&mut self, setindex: &SetIndex) -> Self::Result;
 }
 
 pub trait ExprAccept {
@@ -232,6 +346,12 @@ impl ExprAccept for Expr {
             Self::Set(x) => visitor.visit_set(x),
             Self::This(x) => visitor.visit_this(x),
             Self::SuperExpr(x) => visitor.visit_superexpr(x),
+            Self::Stringify(x) => visitor.visit_stringify(x),
+            Self::Conditional(x) => visitor.visit_conditional(x),
+            Self::CompoundSet(x) => visitor.visit_compoundset(x),
+            Self::Postfix(x) => visitor.visit_postfix(x),
+            Self::Index(x) => visitor.visit_index(x),
+            Self::SetIndex(x) => visitor.visit_setindex(x),
         }
     }
 }
@@ -246,6 +366,9 @@ pub enum Stmt {
     Function(Function),
     ReturnStmt(ReturnStmt),
     Class(Class),
+    TraitStmt(TraitStmt),
+    ThrowStmt(ThrowStmt),
+    TryStmt(TryStmt),
 }
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
@@ -256,7 +379,7 @@ pub struct Block {
 impl Block {
     pub fn new(statements: Vec<Stmt>) -> Self {
         Self {
-            statements: statements,
+            statements,
         }
     }
 }
@@ -269,7 +392,7 @@ pub struct Expression {
 impl Expression {
     pub fn new(expression: Box<Expr>) -> Self {
         Self {
-            expression: expression,
+            expression,
         }
     }
 }
@@ -282,7 +405,7 @@ pub struct Print {
 impl Print {
     pub fn new(expression: Box<Expr>) -> Self {
         Self {
-            expression: expression,
+            expression,
         }
     }
 }
@@ -296,8 +419,8 @@ pub struct Var {
 impl Var {
     pub fn new(name: Token, initializer: Option<Box<Expr>>) -> Self {
         Self {
-            name: name,
-            initializer: initializer,
+            name,
+            initializer,
         }
     }
 }
@@ -312,9 +435,9 @@ pub struct IfStmt {
 impl IfStmt {
     pub fn new(condition: Box<Expr>, then_branch: Box<Stmt>, else_branch: Option<Box<Stmt>>) -> Self {
         Self {
-            condition: condition,
-            then_branch: then_branch,
-            else_branch: else_branch,
+            condition,
+            then_branch,
+            else_branch,
         }
     }
 }
@@ -328,8 +451,8 @@ pub struct WhileStmt {
 impl WhileStmt {
     pub fn new(condition: Box<Expr>, body: Box<Stmt>) -> Self {
         Self {
-            condition: condition,
-            body: body,
+            condition,
+            body,
         }
     }
 }
@@ -339,14 +462,16 @@ pub struct Function {
     pub name: Token,
     pub params: Vec<Token>,
     pub body: Vec<Stmt>,
+    pub is_getter: bool,
 }
 
 impl Function {
-    pub fn new(name: Token, params: Vec<Token>, body: Vec<Stmt>) -> Self {
+    pub fn new(name: Token, params: Vec<Token>, body: Vec<Stmt>, is_getter: bool) -> Self {
         Self {
-            name: name,
-            params: params,
-            body: body,
+            name,
+            params,
+            body,
+            is_getter,
         }
     }
 }
@@ -360,8 +485,8 @@ pub struct ReturnStmt {
 impl ReturnStmt {
     pub fn new(keyword: Token, value: Option<Box<Expr>>) -> Self {
         Self {
-            keyword: keyword,
-            value: value,
+            keyword,
+            value,
         }
     }
 }
@@ -370,15 +495,70 @@ impl ReturnStmt {
 pub struct Class {
     pub name: Token,
     pub superclass: Option<Box<Expr>>,
+    pub traits: Vec<Expr>,
     pub methods: Vec<Function>,
+    pub class_methods: Vec<Function>,
 }
 
 impl Class {
-    pub fn new(name: Token, superclass: Option<Box<Expr>>, methods: Vec<Function>) -> Self {
+    pub fn new(name: Token, superclass: Option<Box<Expr>>, traits: Vec<Expr>, methods: Vec<Function>, class_methods: Vec<Function>) -> Self {
+        Self {
+            name,
+            superclass,
+            traits,
+            methods,
+            class_methods,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct TraitStmt {
+    pub name: Token,
+    pub methods: Vec<Function>,
+    pub class_methods: Vec<Function>,
+}
+
+impl TraitStmt {
+    pub fn new(name: Token, methods: Vec<Function>, class_methods: Vec<Function>) -> Self {
+        Self {
+            name,
+            methods,
+            class_methods,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct ThrowStmt {
+    pub keyword: Token,
+    pub value: Box<Expr>,
+}
+
+impl ThrowStmt {
+    pub fn new(keyword: Token, value: Box<Expr>) -> Self {
+        Self {
+            keyword,
+            value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct TryStmt {
+    pub try_block: Vec<Stmt>,
+    pub catch_name: Option<Token>,
+    pub catch_block: Option<Vec<Stmt>>,
+    pub finally_block: Option<Vec<Stmt>>,
+}
+
+impl TryStmt {
+    pub fn new(try_block: Vec<Stmt>, catch_name: Option<Token>, catch_block: Option<Vec<Stmt>>, finally_block: Option<Vec<Stmt>>) -> Self {
         Self {
-            name: name,
-            superclass: superclass,
-            methods: methods,
+            try_block,
+            catch_name,
+            catch_block,
+            finally_block,
         }
     }
 }
@@ -395,6 +575,9 @@ pub trait StmtVisitor {
     fn visit_function(&mut self, function: &Function) -> Self::Result;
     fn visit_returnstmt(&mut self, returnstmt: &ReturnStmt) -> Self::Result;
     fn visit_class(&mut self, class: &Class) -> Self::Result;
+    fn visit_traitstmt(&mut self, traitstmt: &TraitStmt) -> Self::Result;
+    fn visit_throwstmt(&mut self, throwstmt: &ThrowStmt) -> Self::Result;
+    fn visit_trystmt(&mut self, trystmt: &TryStmt) -> Self::Result;
 }
 
 pub trait StmtAccept {
@@ -413,6 +596,9 @@ impl StmtAccept for Stmt {
             Self::Function(x) => visitor.visit_function(x),
             Self::ReturnStmt(x) => visitor.visit_returnstmt(x),
             Self::Class(x) => visitor.visit_class(x),
+            Self::TraitStmt(x) => visitor.visit_traitstmt(x),
+            Self::ThrowStmt(x) => visitor.visit_throwstmt(x),
+            Self::TryStmt(x) => visitor.visit_trystmt(x),
         }
     }
 }
@@ -1,25 +1,28 @@
 use std::{cell::RefCell, collections::HashMap, error::Error, rc::Rc};
 
 use crate::{
+    gc::{self, Tracer},
     interpreter::{LoxValue, RuntimeError},
     token::{Token, TokenKind},
 };
 
 #[derive(Default, Clone)]
 pub struct Environment {
-    values: HashMap<String, LoxValue>,
+    values: HashMap<Rc<str>, LoxValue>,
     enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
 impl Environment {
     pub fn new(enclosing: Rc<RefCell<Environment>>) -> Self {
         Self {
+            values: HashMap::new(),
             enclosing: Some(enclosing),
-            ..Default::default()
         }
     }
 
-    pub fn define(&mut self, name: String, value: LoxValue) {
+    pub fn define(&mut self, name: impl Into<Rc<str>>, value: LoxValue) {
+        let name = name.into();
+        gc::charge(gc::entries_size([(&name, &value)]));
         self.values.insert(name, value);
     }
 
@@ -44,12 +47,10 @@ impl Environment {
     }
 
     pub fn get_at(&mut self, distance: usize, name: &Token) -> Result<LoxValue, Box<dyn Error>> {
-        let mut environment = Rc::new(RefCell::new(self.clone()));
-        for _ in 0..distance {
-            environment = environment.clone().borrow_mut().enclosing.clone().unwrap();
+        match distance {
+            0 => self.get(name),
+            _ => self.ancestor(distance).borrow_mut().get(name),
         }
-
-        environment.clone().borrow_mut().get(name)
     }
 
     pub fn assign(&mut self, name: &Token, value: LoxValue) -> Result<LoxValue, Box<dyn Error>> {
@@ -80,11 +81,45 @@ impl Environment {
         name: &Token,
         value: LoxValue,
     ) -> Result<LoxValue, Box<dyn Error>> {
-        let mut environment = Rc::new(RefCell::new(self.clone()));
-        for _ in 0..distance {
-            environment = environment.clone().borrow_mut().enclosing.clone().unwrap();
+        match distance {
+            0 => self.assign(name, value),
+            _ => self.ancestor(distance).borrow_mut().assign(name, value),
+        }
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        std::mem::size_of::<RefCell<Environment>>() + gc::entries_size(&self.values)
+    }
+
+    pub(crate) fn trace(&self, tracer: &mut Tracer) {
+        self.values.values().for_each(|value| value.trace(tracer));
+        if let Some(enclosing) = &self.enclosing {
+            tracer.edge(enclosing);
         }
+    }
 
-        environment.clone().borrow_mut().assign(name, value.clone())
+    /// Drops the environment's variables and its link to the enclosing scope, which
+    /// the collector uses to break cycles through garbage closures.
+    pub(crate) fn clear(&mut self) {
+        self.values.clear();
+        self.enclosing = None;
+    }
+
+    fn ancestor(&self, distance: usize) -> Rc<RefCell<Environment>> {
+        let mut environment = self.enclosing.clone().unwrap();
+        for _ in 1..distance {
+            let enclosing = environment.borrow().enclosing.clone().unwrap();
+            environment = enclosing;
+        }
+
+        environment
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        if !self.values.is_empty() {
+            gc::release(std::mem::take(&mut self.values).into_values());
+        }
     }
 }
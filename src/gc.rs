@@ -0,0 +1,515 @@
+//! A cycle collector for the Lox object graph.
+//!
+//! Values are reference counted, which frees most garbage as soon as it becomes
+//! unreachable but leaks cycles, such as an instance holding one of its own bound
+//! methods. Every environment, instance, list, map, class and trait is allocated
+//! through this module, which keeps a weak reference to it. A collection then works
+//! out which of those objects are only referenced by each other:
+//!
+//! 1. Every live object is traced to count how many of its strong references come
+//!    from other tracked objects.
+//! 2. Objects with more strong references than that are held from outside the graph
+//!    (the interpreter's environment stack, Rust locals, the host), so they are roots.
+//! 3. Everything reachable from a root is live. The rest is garbage, and its contents
+//!    are cleared to break the cycles so the reference counts can drop to zero.
+//!
+//! Since roots are found from reference counts, there is no root set to maintain, and
+//! references the collector can't see (such as values captured by natives) only keep
+//! objects alive. Collections run when `gc()` is called and automatically when the
+//! number of tracked objects has doubled since the last one.
+//!
+//! The heap also keeps an estimate of how many bytes the tracked objects use, for
+//! memory limits. Each collection measures the live objects, and between collections
+//! every allocation and store is charged on top of that, so the estimate only errs
+//! on the high side until the next collection corrects it.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap},
+    mem::size_of,
+    ops::{Deref, DerefMut},
+    rc::{Rc, Weak},
+};
+
+use crate::{
+    environment::Environment,
+    interpreter::{Class, Instance, LoxValue, Trait},
+};
+
+/// Tracked objects needed before the first automatic collection.
+const MIN_THRESHOLD: usize = 10_000;
+
+thread_local! {
+    static HEAP: RefCell<Heap> = RefCell::new(Heap::default());
+    /// Bytes charged since the last collection. Kept outside the heap since it is
+    /// updated on every store.
+    static CHARGED: Cell<usize> = const { Cell::new(0) };
+    /// Values waiting to be dropped by the `release` running further up the stack, or
+    /// `None` when there is none.
+    static RELEASING: RefCell<Option<Vec<LoxValue>>> = const { RefCell::new(None) };
+}
+
+#[derive(Default)]
+struct Heap {
+    objects: Vec<Object>,
+    threshold: usize,
+    stats: GcStats,
+    /// Bytes used by the objects that were live after the last collection.
+    measured: usize,
+}
+
+/// Running totals over all collections on this thread.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GcStats {
+    pub collections: usize,
+    pub collected: usize,
+}
+
+/// The objects freed by a single collection, by kind.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Collection {
+    pub environments: usize,
+    pub instances: usize,
+    pub lists: usize,
+    pub maps: usize,
+    pub classes: usize,
+    pub traits: usize,
+    /// Tracked objects still alive after the collection.
+    pub live: usize,
+    /// Estimated bytes used by the live objects.
+    pub bytes: usize,
+}
+
+impl Collection {
+    pub fn collected(&self) -> usize {
+        self.environments + self.instances + self.lists + self.maps + self.classes + self.traits
+    }
+}
+
+enum Object {
+    Environment(Weak<RefCell<Environment>>),
+    Instance(Weak<RefCell<Instance>>),
+    List(Weak<RefCell<ListItems>>),
+    Map(Weak<RefCell<MapEntries>>),
+    Class(Weak<Class>),
+    Trait(Weak<Trait>),
+}
+
+/// A tracked object kept alive for the duration of a collection.
+enum Node {
+    Environment(Rc<RefCell<Environment>>),
+    Instance(Rc<RefCell<Instance>>),
+    List(Rc<RefCell<ListItems>>),
+    Map(Rc<RefCell<MapEntries>>),
+    Class(Rc<Class>),
+    Trait(Rc<Trait>),
+}
+
+impl Object {
+    fn upgrade(&self) -> Option<Node> {
+        Some(match self {
+            Self::Environment(weak) => Node::Environment(weak.upgrade()?),
+            Self::Instance(weak) => Node::Instance(weak.upgrade()?),
+            Self::List(weak) => Node::List(weak.upgrade()?),
+            Self::Map(weak) => Node::Map(weak.upgrade()?),
+            Self::Class(weak) => Node::Class(weak.upgrade()?),
+            Self::Trait(weak) => Node::Trait(weak.upgrade()?),
+        })
+    }
+}
+
+impl Node {
+    fn downgrade(&self) -> Object {
+        match self {
+            Self::Environment(rc) => Object::Environment(Rc::downgrade(rc)),
+            Self::Instance(rc) => Object::Instance(Rc::downgrade(rc)),
+            Self::List(rc) => Object::List(Rc::downgrade(rc)),
+            Self::Map(rc) => Object::Map(Rc::downgrade(rc)),
+            Self::Class(rc) => Object::Class(Rc::downgrade(rc)),
+            Self::Trait(rc) => Object::Trait(Rc::downgrade(rc)),
+        }
+    }
+
+    fn id(&self) -> usize {
+        match self {
+            Self::Environment(rc) => id(rc),
+            Self::Instance(rc) => id(rc),
+            Self::List(rc) => id(rc),
+            Self::Map(rc) => id(rc),
+            Self::Class(rc) => id(rc),
+            Self::Trait(rc) => id(rc),
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Self::Environment(rc) => Rc::strong_count(rc),
+            Self::Instance(rc) => Rc::strong_count(rc),
+            Self::List(rc) => Rc::strong_count(rc),
+            Self::Map(rc) => Rc::strong_count(rc),
+            Self::Class(rc) => Rc::strong_count(rc),
+            Self::Trait(rc) => Rc::strong_count(rc),
+        }
+    }
+
+    /// Estimated bytes used by the object itself and the strings it holds. Returns
+    /// nothing for objects that are borrowed, which only happens to live ones.
+    fn size(&self) -> usize {
+        match self {
+            Self::Environment(rc) => rc.try_borrow().map_or(0, |environment| environment.size()),
+            Self::Instance(rc) => rc.try_borrow().map_or(0, |instance| {
+                size_of::<RefCell<Instance>>() + entries_size(instance.fields())
+            }),
+            Self::List(rc) => rc.try_borrow().map_or(0, |list| {
+                size_of::<RefCell<ListItems>>()
+                    + (list.capacity() - list.len()) * size_of::<LoxValue>()
+                    + list.iter().map(value_size).sum::<usize>()
+            }),
+            Self::Map(rc) => rc.try_borrow().map_or(0, |map| {
+                size_of::<RefCell<MapEntries>>() + entries_size(map.iter())
+            }),
+            Self::Class(rc) => rc.size(),
+            Self::Trait(_) => size_of::<Trait>(),
+        }
+    }
+
+    /// Records the objects this one holds strong references to. Returns false if the
+    /// object is borrowed and can't be inspected right now.
+    fn trace(&self, tracer: &mut Tracer) -> bool {
+        match self {
+            Self::Environment(rc) => match rc.try_borrow() {
+                Ok(environment) => environment.trace(tracer),
+                Err(_) => return false,
+            },
+            Self::Instance(rc) => match rc.try_borrow() {
+                Ok(instance) => instance.trace(tracer),
+                Err(_) => return false,
+            },
+            Self::List(rc) => match rc.try_borrow() {
+                Ok(list) => list.iter().for_each(|item| item.trace(tracer)),
+                Err(_) => return false,
+            },
+            Self::Map(rc) => match rc.try_borrow() {
+                Ok(map) => map.values().for_each(|value| value.trace(tracer)),
+                Err(_) => return false,
+            },
+            Self::Class(rc) => return rc.trace(tracer),
+            Self::Trait(rc) => rc.trace(tracer),
+        }
+
+        true
+    }
+
+    /// Drops everything the object holds, breaking any cycles through it.
+    fn clear(&self, collection: &mut Collection) {
+        match self {
+            Self::Environment(rc) => {
+                if let Ok(mut environment) = rc.try_borrow_mut() {
+                    environment.clear();
+                }
+                collection.environments += 1;
+            }
+            Self::Instance(rc) => {
+                if let Ok(mut instance) = rc.try_borrow_mut() {
+                    instance.fields_mut().clear();
+                }
+                collection.instances += 1;
+            }
+            Self::List(rc) => {
+                if let Ok(mut list) = rc.try_borrow_mut() {
+                    list.clear();
+                }
+                collection.lists += 1;
+            }
+            Self::Map(rc) => {
+                if let Ok(mut map) = rc.try_borrow_mut() {
+                    map.clear();
+                }
+                collection.maps += 1;
+            }
+            // Class methods and traits only reach other objects through closures,
+            // which are cleared as environments
+            Self::Class(rc) => {
+                rc.clear_fields();
+                collection.classes += 1;
+            }
+            Self::Trait(_) => collection.traits += 1,
+        }
+    }
+}
+
+/// Estimated bytes for a value held in an environment, object or collection.
+pub(crate) fn value_size(value: &LoxValue) -> usize {
+    match value {
+        LoxValue::String(s) => size_of::<LoxValue>() + s.len(),
+        _ => size_of::<LoxValue>(),
+    }
+}
+
+/// Estimated bytes for a table of named values, such as fields or map entries.
+pub(crate) fn entries_size<'a>(
+    entries: impl IntoIterator<Item = (&'a Rc<str>, &'a LoxValue)>,
+) -> usize {
+    entries
+        .into_iter()
+        .map(|(name, value)| size_of::<Rc<str>>() + name.len() + value_size(value))
+        .sum()
+}
+
+/// Adds to the estimated memory use, for allocations and stores that grow the heap.
+pub fn charge(bytes: usize) {
+    CHARGED.with(|charged| charged.set(charged.get().saturating_add(bytes)));
+}
+
+/// The estimated bytes in use: what the last collection measured plus everything
+/// charged since.
+pub fn usage() -> usize {
+    HEAP.with(|heap| heap.borrow().measured)
+        .saturating_add(CHARGED.with(Cell::get))
+}
+
+/// The items of a list. They are freed through `release`, so a list nested many
+/// levels deep can be freed without overflowing the stack.
+#[derive(Debug, Default)]
+pub struct ListItems(Vec<LoxValue>);
+
+impl Deref for ListItems {
+    type Target = Vec<LoxValue>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for ListItems {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Drop for ListItems {
+    fn drop(&mut self) {
+        if !self.0.is_empty() {
+            release(std::mem::take(&mut self.0));
+        }
+    }
+}
+
+/// The entries of a map, freed through `release` like the items of a list.
+#[derive(Debug, Default)]
+pub struct MapEntries(BTreeMap<Rc<str>, LoxValue>);
+
+impl Deref for MapEntries {
+    type Target = BTreeMap<Rc<str>, LoxValue>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for MapEntries {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Drop for MapEntries {
+    fn drop(&mut self) {
+        if !self.0.is_empty() {
+            release(std::mem::take(&mut self.0).into_values());
+        }
+    }
+}
+
+fn id<T: ?Sized>(rc: &Rc<T>) -> usize {
+    Rc::as_ptr(rc) as *const () as usize
+}
+
+/// Collects the identities of the tracked objects a value refers to.
+#[derive(Default)]
+pub struct Tracer {
+    edges: Vec<usize>,
+}
+
+impl Tracer {
+    pub fn edge<T: ?Sized>(&mut self, rc: &Rc<T>) {
+        self.edges.push(id(rc));
+    }
+}
+
+fn track(node: Node) {
+    charge(node.size());
+    HEAP.with(|heap| heap.borrow_mut().objects.push(node.downgrade()));
+}
+
+pub fn environment(environment: Environment) -> Rc<RefCell<Environment>> {
+    let rc = Rc::new(RefCell::new(environment));
+    track(Node::Environment(rc.clone()));
+    rc
+}
+
+pub fn instance(instance: Instance) -> Rc<RefCell<Instance>> {
+    let rc = Rc::new(RefCell::new(instance));
+    track(Node::Instance(rc.clone()));
+    rc
+}
+
+pub fn list(items: Vec<LoxValue>) -> Rc<RefCell<ListItems>> {
+    let rc = Rc::new(RefCell::new(ListItems(items)));
+    track(Node::List(rc.clone()));
+    rc
+}
+
+pub fn map(entries: BTreeMap<Rc<str>, LoxValue>) -> Rc<RefCell<MapEntries>> {
+    let rc = Rc::new(RefCell::new(MapEntries(entries)));
+    track(Node::Map(rc.clone()));
+    rc
+}
+
+pub fn class(class: Class) -> Rc<Class> {
+    let rc = Rc::new(class);
+    track(Node::Class(rc.clone()));
+    rc
+}
+
+pub fn mixin(mixin: Trait) -> Rc<Trait> {
+    let rc = Rc::new(mixin);
+    track(Node::Trait(rc.clone()));
+    rc
+}
+
+/// Collects if enough objects have been allocated since the last collection. The
+/// interpreter calls this at points where it holds no borrows of Lox objects.
+pub fn maybe_collect() {
+    let due = HEAP.with(|heap| {
+        let heap = heap.borrow();
+        heap.objects.len() >= heap.threshold.max(MIN_THRESHOLD)
+    });
+
+    if due {
+        collect();
+    }
+}
+
+/// Frees every tracked object that is only reachable through cycles.
+pub fn collect() -> Collection {
+    // Take the objects out so the heap isn't borrowed while values are dropped
+    let objects = HEAP.with(|heap| std::mem::take(&mut heap.borrow_mut().objects));
+    let nodes: Vec<Node> = objects.iter().filter_map(Object::upgrade).collect();
+    drop(objects);
+
+    let index: HashMap<usize, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id(), i))
+        .collect();
+
+    let mut internal = vec![0; nodes.len()];
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    let mut roots: Vec<usize> = Vec::new();
+    for (i, node) in nodes.iter().enumerate() {
+        let mut tracer = Tracer::default();
+        if !node.trace(&mut tracer) {
+            // Something is using the object right now, so it is certainly alive
+            roots.push(i);
+        }
+
+        for edge in tracer.edges {
+            if let Some(&child) = index.get(&edge) {
+                internal[child] += 1;
+                children[i].push(child);
+            }
+        }
+    }
+
+    // One of the strong references is the node we upgraded for this collection
+    roots.extend((0..nodes.len()).filter(|&i| nodes[i].strong_count() - 1 > internal[i]));
+
+    let mut live = vec![false; nodes.len()];
+    while let Some(i) = roots.pop() {
+        if !live[i] {
+            live[i] = true;
+            roots.extend(&children[i]);
+        }
+    }
+
+    let mut collection = Collection::default();
+    for (node, _) in nodes.iter().zip(&live).filter(|(_, live)| !**live) {
+        node.clear(&mut collection);
+    }
+    collection.live = live.iter().filter(|live| **live).count();
+    collection.bytes = nodes
+        .iter()
+        .zip(&live)
+        .filter(|(_, live)| **live)
+        .map(|(node, _)| node.size())
+        .sum();
+
+    let survivors: Vec<Object> = nodes
+        .iter()
+        .zip(&live)
+        .filter(|(_, live)| **live)
+        .map(|(node, _)| node.downgrade())
+        .collect();
+    drop(nodes);
+
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        // Objects allocated while values were being dropped are already registered
+        heap.objects.extend(survivors);
+        heap.threshold = heap.objects.len() * 2;
+        heap.measured = collection.bytes;
+        heap.stats.collections += 1;
+        heap.stats.collected += collection.collected();
+    });
+    CHARGED.with(|charged| charged.set(0));
+
+    collection
+}
+
+pub fn stats() -> GcStats {
+    HEAP.with(|heap| heap.borrow().stats)
+}
+
+/// The number of objects currently tracked, including ones freed since the last
+/// collection that haven't been pruned yet.
+pub fn tracked() -> usize {
+    HEAP.with(|heap| heap.borrow().objects.len())
+}
+
+/// Drops the contents of an object that is being freed. Dropping a value frees what
+/// only it refers to, so a long chain of objects, such as a linked list of instances,
+/// would otherwise be freed by recursing once per link and could overflow the stack.
+/// Objects, lists and maps call this from `Drop` instead: the outermost call frees the
+/// values one at a time, and calls made while it runs only queue theirs. Values that
+/// don't free an object are dropped straight away, since they can't start a chain.
+pub(crate) fn release(values: impl IntoIterator<Item = LoxValue>) {
+    let mut values = values.into_iter().filter(LoxValue::frees_object).peekable();
+    if values.peek().is_none() {
+        return;
+    }
+
+    let outermost = RELEASING.with(|releasing| {
+        let mut releasing = releasing.borrow_mut();
+        match &mut *releasing {
+            Some(pending) => {
+                pending.extend(values);
+                false
+            }
+            None => {
+                *releasing = Some(values.collect());
+                true
+            }
+        }
+    });
+    if !outermost {
+        return;
+    }
+
+    while let Some(value) =
+        RELEASING.with(|releasing| releasing.borrow_mut().as_mut().and_then(Vec::pop))
+    {
+        drop(value);
+    }
+    RELEASING.with(|releasing| *releasing.borrow_mut() = None);
+}
@@ -0,0 +1,26 @@
+//! Identifier interning. The scanner interns every identifier, so all uses of a name
+//! share one allocation: tokens, environment keys and field names clone an `Rc`
+//! instead of copying the text, and comparing two interned names usually stops at the
+//! pointer check.
+
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
+
+thread_local! {
+    static NAMES: RefCell<HashSet<Rc<str>>> = RefCell::new(HashSet::new());
+}
+
+/// Returns the shared copy of `name`, adding it on first use. Interned names live as
+/// long as the thread, which is fine for identifiers since a program only has so many.
+pub fn intern(name: &str) -> Rc<str> {
+    NAMES.with(|names| {
+        let mut names = names.borrow_mut();
+        match names.get(name) {
+            Some(interned) => interned.clone(),
+            None => {
+                let interned: Rc<str> = Rc::from(name);
+                names.insert(interned.clone());
+                interned
+            }
+        }
+    })
+}
@@ -1,57 +1,191 @@
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     error::Error,
     fmt::{Debug, Display},
+    ops::RangeInclusive,
     rc::Rc,
-    time::{SystemTime, UNIX_EPOCH},
+    time::Duration,
 };
 
 use crate::{
     ast::{Expr, ExprAccept, ExprVisitor, Stmt, StmtAccept, StmtVisitor},
     environment::Environment,
+    gc::{self, ListItems, MapEntries, Tracer},
+    intern::intern,
+    limits::{Budget, CancelHandle, LimitError},
+    stdlib::{
+        self,
+        math::Random,
+        time::{Clock, VirtualClock},
+        Capability,
+    },
     token::{Token, TokenKind},
 };
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone)]
 pub enum LoxValue {
     Nil,
     Boolean(bool),
     Number(f64),
-    String(String),
+    String(Rc<str>),
     Function(Function),
     NativeFunction(NativeFunction),
-    Class(Class),
+    Class(Rc<Class>),
+    Trait(Rc<Trait>),
     Instance(Rc<RefCell<Instance>>),
+    List(Rc<RefCell<ListItems>>),
+    Map(Rc<RefCell<MapEntries>>),
 }
 
 impl LoxValue {
     pub fn is_truthy(&self) -> bool {
         match self {
             LoxValue::Nil => false,
-            LoxValue::Boolean(b) => b.clone(),
+            LoxValue::Boolean(b) => *b,
             _ => true,
         }
     }
+
+    /// The name of the value's type, as reported by the `type()` native.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Nil => "nil",
+            Self::Boolean(_) => "boolean",
+            Self::Number(_) => "number",
+            Self::String(_) => "string",
+            Self::Function(_) | Self::NativeFunction(_) => "function",
+            Self::Class(_) => "class",
+            Self::Trait(_) => "trait",
+            Self::Instance(_) => "instance",
+            Self::List(_) => "list",
+            Self::Map(_) => "map",
+        }
+    }
+
+    /// Records the collector-tracked objects this value refers to.
+    pub(crate) fn trace(&self, tracer: &mut Tracer) {
+        match self {
+            Self::Function(fun) => tracer.edge(&fun.closure),
+            Self::Class(class) => tracer.edge(class),
+            Self::Trait(mixin) => tracer.edge(mixin),
+            Self::Instance(instance) => tracer.edge(instance),
+            Self::List(list) => tracer.edge(list),
+            Self::Map(map) => tracer.edge(map),
+            Self::Nil
+            | Self::Boolean(_)
+            | Self::Number(_)
+            | Self::String(_)
+            | Self::NativeFunction(_) => {}
+        }
+    }
+
+    /// Whether dropping the value frees a collector-tracked object, and with it
+    /// everything that only that object refers to.
+    pub(crate) fn frees_object(&self) -> bool {
+        match self {
+            Self::Function(fun) => Rc::strong_count(&fun.closure) == 1,
+            Self::Class(class) => Rc::strong_count(class) == 1,
+            Self::Trait(mixin) => Rc::strong_count(mixin) == 1,
+            Self::Instance(instance) => Rc::strong_count(instance) == 1,
+            Self::List(list) => Rc::strong_count(list) == 1,
+            Self::Map(map) => Rc::strong_count(map) == 1,
+            Self::Nil
+            | Self::Boolean(_)
+            | Self::Number(_)
+            | Self::String(_)
+            | Self::NativeFunction(_) => false,
+        }
+    }
 }
 
 impl Display for LoxValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_nested(f, &mut Vec::new())
+    }
+}
+
+impl LoxValue {
+    /// Formats the value for `Display`. `containers` holds the lists and maps currently
+    /// being printed, so a list that contains itself prints as `[...]` (and a map as
+    /// `{...}`) instead of recursing forever.
+    fn fmt_nested(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        containers: &mut Vec<*const ()>,
+    ) -> std::fmt::Result {
         match self {
             Self::Nil => write!(f, "nil"),
             Self::Boolean(b) => write!(f, "{}", b),
             Self::String(s) => write!(f, "{}", s),
             Self::Number(x) => write!(f, "{}", x.to_string().trim_end_matches(".0")),
-            Self::Function(fun) => write!(f, "{:?}", fun),
-            Self::NativeFunction(nfun) => write!(f, "{:?}", nfun),
-            Self::Class(c) => write!(f, "{:?}", c),
-            Self::Instance(i) => write!(f, "{:?}", i),
+            Self::Function(fun) => write!(f, "<fn {}>", fun.name()),
+            Self::NativeFunction(_) => write!(f, "<native fn>"),
+            Self::Class(c) => write!(f, "{}", c.name),
+            Self::Trait(t) => write!(f, "{}", t.name),
+            Self::Instance(i) => write!(f, "{} instance", i.borrow().class.name),
+            Self::List(list) => {
+                let container = Rc::as_ptr(list) as *const ();
+                if containers.contains(&container) {
+                    return write!(f, "[...]");
+                }
+                containers.push(container);
+                write!(f, "[")?;
+                for (i, item) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.fmt_nested(f, containers)?;
+                }
+                containers.pop();
+                write!(f, "]")
+            }
+            Self::Map(map) => {
+                let container = Rc::as_ptr(map) as *const ();
+                if containers.contains(&container) {
+                    return write!(f, "{{...}}");
+                }
+                containers.push(container);
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", key)?;
+                    value.fmt_nested(f, containers)?;
+                }
+                containers.pop();
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Lox equality. Values of different types are never equal, numbers follow IEEE 754
+/// (so `NaN != NaN`), strings compare by content and everything else, from functions
+/// to lists, compares by identity.
+impl PartialEq for LoxValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Nil, Self::Nil) => true,
+            (Self::Boolean(a), Self::Boolean(b)) => a == b,
+            (Self::Number(a), Self::Number(b)) => a == b,
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Function(a), Self::Function(b)) => a == b,
+            (Self::NativeFunction(a), Self::NativeFunction(b)) => a == b,
+            (Self::Class(a), Self::Class(b)) => Rc::ptr_eq(a, b),
+            (Self::Trait(a), Self::Trait(b)) => Rc::ptr_eq(a, b),
+            (Self::Instance(a), Self::Instance(b)) => Rc::ptr_eq(a, b),
+            (Self::List(a), Self::List(b)) => Rc::ptr_eq(a, b),
+            (Self::Map(a), Self::Map(b)) => Rc::ptr_eq(a, b),
+            _ => false,
         }
     }
 }
 
 trait LoxCallable {
-    fn arity(&self) -> usize;
+    /// The range of argument counts the callable accepts.
+    fn arity(&self) -> RangeInclusive<usize>;
     fn call(
         &self,
         interpreter: &mut Interpreter,
@@ -59,57 +193,63 @@ trait LoxCallable {
     ) -> Result<LoxValue, Box<dyn Error>>;
 }
 
+pub type NativeFn = dyn Fn(&mut Interpreter, Vec<LoxValue>) -> Result<LoxValue, Box<dyn Error>>;
+
 #[derive(Clone)]
 pub struct NativeFunction {
-    function: Rc<dyn Fn() -> LoxValue>,
+    name: Rc<str>,
+    arity: RangeInclusive<usize>,
+    function: Rc<NativeFn>,
 }
 
 impl NativeFunction {
-    pub fn new(function: Rc<dyn Fn() -> LoxValue>) -> Self {
-        Self { function }
+    pub fn new(name: Rc<str>, arity: RangeInclusive<usize>, function: Rc<NativeFn>) -> Self {
+        Self {
+            name,
+            arity,
+            function,
+        }
     }
 }
 
 impl LoxCallable for NativeFunction {
-    fn arity(&self) -> usize {
-        0
+    fn arity(&self) -> RangeInclusive<usize> {
+        self.arity.clone()
     }
 
     fn call(
         &self,
-        _interpreter: &mut Interpreter,
-        _arguments: Vec<LoxValue>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<LoxValue>,
     ) -> Result<LoxValue, Box<dyn Error>> {
-        Ok((self.function)())
+        (self.function)(interpreter, arguments)
     }
 }
 
 impl Debug for NativeFunction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        write!(f, "NativeFunction: {{ {} }}", self.name)
     }
 }
 
+/// Natives are equal when they share the same underlying closure, so `clock == clock`
+/// but each lookup of a method such as `list.push` is a new native.
 impl PartialEq for NativeFunction {
-    fn eq(&self, _other: &Self) -> bool {
-        false
-    }
-
-    fn ne(&self, other: &Self) -> bool {
-        !self.eq(other)
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.function, &other.function)
     }
 }
 
 #[derive(Clone)]
 pub struct Function {
-    declaration: crate::ast::Function,
+    declaration: Rc<crate::ast::Function>,
     closure: Rc<RefCell<Environment>>,
     is_initializer: bool,
 }
 
 impl Function {
     pub fn new(
-        declaration: crate::ast::Function,
+        declaration: Rc<crate::ast::Function>,
         closure: Rc<RefCell<Environment>>,
         is_initializer: bool,
     ) -> Self {
@@ -120,20 +260,40 @@ impl Function {
         }
     }
 
+    pub fn name(&self) -> &str {
+        match self.declaration.name.kind() {
+            TokenKind::Identifier(id) => id,
+            _ => "",
+        }
+    }
+
+    pub fn is_getter(&self) -> bool {
+        self.declaration.is_getter
+    }
+
     pub fn bind(&self, instance: Rc<RefCell<Instance>>) -> LoxValue {
+        self.bind_this(LoxValue::Instance(instance))
+    }
+
+    /// Binds a class method, where `this` is the class it was called on.
+    pub fn bind_class(&self, class: Rc<Class>) -> LoxValue {
+        self.bind_this(LoxValue::Class(class))
+    }
+
+    fn bind_this(&self, this: LoxValue) -> LoxValue {
         let mut environment = Environment::new(self.closure.clone());
-        environment.define("this".to_string(), LoxValue::Instance(instance));
+        environment.define("this".to_string(), this);
         LoxValue::Function(Function::new(
             self.declaration.clone(),
-            Rc::new(RefCell::new(environment)),
+            gc::environment(environment),
             self.is_initializer,
         ))
     }
 }
 
 impl LoxCallable for Function {
-    fn arity(&self) -> usize {
-        self.declaration.params.len()
+    fn arity(&self) -> RangeInclusive<usize> {
+        self.declaration.params.len()..=self.declaration.params.len()
     }
 
     fn call(
@@ -141,6 +301,25 @@ impl LoxCallable for Function {
         interpreter: &mut Interpreter,
         arguments: Vec<LoxValue>,
     ) -> Result<LoxValue, Box<dyn Error>> {
+        interpreter.budget.step()?;
+        interpreter.budget.enter_call()?;
+        let result = self.execute(interpreter, arguments);
+        interpreter.budget.exit_call();
+
+        result
+    }
+}
+
+impl Function {
+    /// Runs the body with the arguments bound to the parameters. `call` wraps it so the
+    /// call depth is counted on every way out.
+    fn execute(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<LoxValue>,
+    ) -> Result<LoxValue, Box<dyn Error>> {
+        gc::maybe_collect();
+
         let mut environment = Environment::new(self.closure.clone());
 
         for (i, parameter) in self.declaration.params.iter().enumerate() {
@@ -156,7 +335,7 @@ impl LoxCallable for Function {
         }
 
         let previous = interpreter.environment.clone();
-        interpreter.environment = Rc::new(RefCell::new(environment));
+        interpreter.environment = gc::environment(environment);
 
         for statement in &self.declaration.body {
             match statement.accept(&mut *interpreter) {
@@ -166,10 +345,10 @@ impl LoxCallable for Function {
 
                     if let Some(return_err) = err.downcast_ref::<ReturnError>() {
                         if self.is_initializer {
-                            return self.closure.borrow_mut().get_at(
-                                0,
-                                &Token::new(TokenKind::Identifier("this".to_string()), 0),
-                            );
+                            return self
+                                .closure
+                                .borrow_mut()
+                                .get_at(0, &Token::new(TokenKind::Identifier(intern("this")), 0));
                         }
                         return Ok(return_err.value.clone());
                     }
@@ -184,7 +363,7 @@ impl LoxCallable for Function {
             return self
                 .closure
                 .borrow_mut()
-                .get_at(0, &Token::new(TokenKind::Identifier("this".to_string()), 0));
+                .get_at(0, &Token::new(TokenKind::Identifier(intern("this")), 0));
         }
         Ok(LoxValue::Nil)
     }
@@ -192,41 +371,135 @@ impl LoxCallable for Function {
 
 impl Debug for Function {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Function: {{ {:?}, closure }}", self.declaration)
+        write!(f, "Function: {{ {} }}", self.name())
     }
 }
 
+/// Functions are equal when they come from the same evaluation of the same declaration.
+/// Binding a method creates a new closure, so each `object.method` access is a new
+/// function.
 impl PartialEq for Function {
-    fn eq(&self, _other: &Self) -> bool {
-        false
-    }
-
-    fn ne(&self, other: &Self) -> bool {
-        !self.eq(other)
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.declaration, &other.declaration)
+            && Rc::ptr_eq(&self.closure, &other.closure)
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// A class declared in Lox. Classes are shared behind `Rc`, so instances and subclasses
+/// refer to the same class rather than copies of it, and two class values are equal
+/// only if they are the same class.
+#[derive(Debug)]
 pub struct Class {
-    name: String,
-    superclass: Option<Box<Class>>,
-    methods: HashMap<String, Function>,
+    name: Rc<str>,
+    superclass: Option<Rc<Class>>,
+    methods: HashMap<Rc<str>, Function>,
+    class_methods: HashMap<Rc<str>, Function>,
+    fields: RefCell<HashMap<Rc<str>, LoxValue>>,
 }
 
 impl Class {
     pub fn new(
-        name: String,
-        superclass: Option<Box<Class>>,
-        methods: HashMap<String, Function>,
+        name: Rc<str>,
+        superclass: Option<Rc<Class>>,
+        methods: HashMap<Rc<str>, Function>,
+        class_methods: HashMap<Rc<str>, Function>,
     ) -> Self {
         Self {
             name,
             superclass,
             methods,
+            class_methods,
+            fields: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Looks up a static field or class method, searching superclasses too. Class
+    /// methods are bound to this class, so a method inherited by a subclass sees the
+    /// subclass as `this`.
+    pub fn get(self: &Rc<Self>, name: &Token) -> Result<LoxValue, Box<dyn Error>> {
+        let id = match name.kind() {
+            TokenKind::Identifier(id) => id,
+            _ => {
+                return Err(Box::new(RuntimeError::new(
+                    name.clone(),
+                    "Expected identifier".to_string(),
+                )))
+            }
+        };
+
+        if let Some(value) = self.find_field(id) {
+            return Ok(value);
+        }
+
+        if let Some(method) = self.find_class_method(id) {
+            return Ok(method.bind_class(self.clone()));
         }
+
+        Err(Box::new(RuntimeError::new(
+            name.clone(),
+            format!("Undefined property '{}' on class {}", id, self.name),
+        )))
+    }
+
+    /// Sets a static field on this class. A subclass assigning an inherited field gets
+    /// its own copy rather than changing the superclass's.
+    pub fn set(&self, name: &Token, value: LoxValue) {
+        if let TokenKind::Identifier(id) = name.kind() {
+            gc::charge(gc::entries_size([(id, &value)]));
+            self.fields.borrow_mut().insert(id.clone(), value);
+        }
+    }
+
+    fn find_field(&self, name: &str) -> Option<LoxValue> {
+        match self.fields.borrow().get(name) {
+            Some(value) => Some(value.clone()),
+            None => match &self.superclass {
+                Some(superclass) => superclass.find_field(name),
+                None => None,
+            },
+        }
+    }
+
+    pub fn find_class_method(&self, name: &str) -> Option<&Function> {
+        match self.class_methods.get(name) {
+            Some(method) => Some(method),
+            None => match &self.superclass {
+                Some(superclass) => superclass.find_class_method(name),
+                None => None,
+            },
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn superclass(&self) -> Option<&Rc<Class>> {
+        self.superclass.as_ref()
+    }
+
+    /// Names of the instance methods the class defines or inherits, sorted.
+    pub fn method_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.methods.keys().map(|name| name.to_string()).collect();
+        if let Some(superclass) = &self.superclass {
+            names.extend(superclass.method_names());
+        }
+        names.sort();
+        names.dedup();
+
+        names
+    }
+
+    /// Whether this class is `other` or inherits from it.
+    pub fn is_subclass_of(&self, other: &Class) -> bool {
+        self == other
+            || self
+                .superclass
+                .as_ref()
+                .is_some_and(|superclass| superclass.is_subclass_of(other))
     }
 
-    pub fn find_method(&self, name: &String) -> Option<&Function> {
+    pub fn find_method(&self, name: &str) -> Option<&Function> {
         match self.methods.get(name) {
             Some(method) => Some(method),
             None => match &self.superclass {
@@ -235,14 +508,54 @@ impl Class {
             },
         }
     }
+
+    pub(crate) fn size(&self) -> usize {
+        let methods = self.methods.len() + self.class_methods.len();
+        std::mem::size_of::<Class>()
+            + methods * std::mem::size_of::<(Rc<str>, Function)>()
+            + self
+                .fields
+                .try_borrow()
+                .map_or(0, |fields| gc::entries_size(&*fields))
+    }
+
+    /// Returns false if the static fields are borrowed and couldn't be traced.
+    pub(crate) fn trace(&self, tracer: &mut Tracer) -> bool {
+        if let Some(superclass) = &self.superclass {
+            tracer.edge(superclass);
+        }
+        for method in self.methods.values().chain(self.class_methods.values()) {
+            tracer.edge(&method.closure);
+        }
+
+        match self.fields.try_borrow() {
+            Ok(fields) => {
+                fields.values().for_each(|value| value.trace(tracer));
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub(crate) fn clear_fields(&self) {
+        if let Ok(mut fields) = self.fields.try_borrow_mut() {
+            fields.clear();
+        }
+    }
 }
 
-impl LoxCallable for Class {
-    fn arity(&self) -> usize {
-        let initializer = self.find_method(&"init".to_string());
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl LoxCallable for Rc<Class> {
+    fn arity(&self) -> RangeInclusive<usize> {
+        let initializer = self.find_method("init");
         match initializer {
             Some(initializer) => initializer.arity(),
-            None => 0,
+            None => 0..=0,
         }
     }
 
@@ -251,31 +564,92 @@ impl LoxCallable for Class {
         interpreter: &mut Interpreter,
         arguments: Vec<LoxValue>,
     ) -> Result<LoxValue, Box<dyn Error>> {
-        let instance = Rc::new(RefCell::new(Instance::new(self.clone())));
-        let initializer = self.find_method(&"init".to_string());
+        let instance = gc::instance(Instance::new(self.clone()));
+        let initializer = self.find_method("init");
         if let Some(initializer) = initializer {
             if let LoxValue::Function(fun) = initializer.bind(instance.clone()) {
-                let _ = fun.call(interpreter, arguments);
+                fun.call(interpreter, arguments)?;
             }
         }
-        Ok(LoxValue::Instance(instance.clone()))
+        Ok(LoxValue::Instance(instance))
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// A named set of methods that classes mix in with `with`. The methods are copied into
+/// each class that uses the trait.
+#[derive(Debug)]
+pub struct Trait {
+    name: Rc<str>,
+    methods: HashMap<Rc<str>, Function>,
+    class_methods: HashMap<Rc<str>, Function>,
+}
+
+impl Trait {
+    pub fn new(
+        name: Rc<str>,
+        methods: HashMap<Rc<str>, Function>,
+        class_methods: HashMap<Rc<str>, Function>,
+    ) -> Self {
+        Self {
+            name,
+            methods,
+            class_methods,
+        }
+    }
+
+    pub(crate) fn trace(&self, tracer: &mut Tracer) {
+        for method in self.methods.values().chain(self.class_methods.values()) {
+            tracer.edge(&method.closure);
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct Instance {
-    class: Class,
-    fields: HashMap<String, LoxValue>,
+    class: Rc<Class>,
+    fields: HashMap<Rc<str>, LoxValue>,
+    // Set for namespaces such as `math`, whose fields scripts can't change
+    read_only: bool,
 }
 
 impl Instance {
-    pub fn new(class: Class) -> Self {
+    pub fn new(class: Rc<Class>) -> Self {
+        Self::with_fields(class, HashMap::new())
+    }
+
+    pub fn with_fields(class: Rc<Class>, fields: HashMap<Rc<str>, LoxValue>) -> Self {
+        Self {
+            class,
+            fields,
+            read_only: false,
+        }
+    }
+
+    /// An instance whose fields can't be set, added or replaced after creation.
+    pub fn read_only(class: Rc<Class>, fields: HashMap<Rc<str>, LoxValue>) -> Self {
         Self {
             class,
-            fields: HashMap::new(),
+            fields,
+            read_only: true,
         }
     }
 
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn class(&self) -> &Rc<Class> {
+        &self.class
+    }
+
+    pub fn fields(&self) -> &HashMap<Rc<str>, LoxValue> {
+        &self.fields
+    }
+
+    pub fn fields_mut(&mut self) -> &mut HashMap<Rc<str>, LoxValue> {
+        &mut self.fields
+    }
+
     pub fn get(
         &self,
         name: &Token,
@@ -300,19 +674,45 @@ impl Instance {
             return Ok(method.bind(instance));
         }
 
-        return Err(Box::new(RuntimeError::new(
+        Err(Box::new(RuntimeError::new(
             name.clone(),
             format!("Undefined property '{}'", id),
-        )));
+        )))
     }
 
-    pub fn set(&mut self, name: &Token, value: LoxValue) {
+    pub fn set(&mut self, name: &Token, value: LoxValue) -> Result<(), Box<dyn Error>> {
         let id = match name.kind() {
             TokenKind::Identifier(id) => id.clone(),
-            _ => return,
+            _ => return Ok(()),
         };
 
+        if self.read_only {
+            return Err(Box::new(RuntimeError::new(
+                name.clone(),
+                format!(
+                    "Can't set property '{}' of read-only '{}'",
+                    id,
+                    self.class.name()
+                ),
+            )));
+        }
+
+        gc::charge(gc::entries_size([(&id, &value)]));
         self.fields.insert(id, value);
+        Ok(())
+    }
+
+    pub(crate) fn trace(&self, tracer: &mut Tracer) {
+        tracer.edge(&self.class);
+        self.fields.values().for_each(|value| value.trace(tracer));
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        if !self.fields.is_empty() {
+            gc::release(std::mem::take(&mut self.fields).into_values());
+        }
     }
 }
 
@@ -320,11 +720,37 @@ impl Instance {
 pub struct RuntimeError {
     token: Token,
     message: String,
+    backtrace: Vec<String>,
 }
 
 impl RuntimeError {
     pub fn new(token: Token, message: String) -> Self {
-        Self { token, message }
+        Self {
+            token,
+            message,
+            backtrace: Vec::new(),
+        }
+    }
+
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Converts the error into the `Error` instance that a Lox `catch` clause receives.
+    fn to_lox_value(&self, class: Rc<Class>) -> LoxValue {
+        let fields = HashMap::from([
+            (
+                "message".into(),
+                LoxValue::String(self.message.as_str().into()),
+            ),
+            ("line".into(), LoxValue::Number(*self.token.line() as f64)),
+        ]);
+
+        LoxValue::Instance(gc::instance(Instance::with_fields(class, fields)))
     }
 }
 
@@ -338,7 +764,54 @@ impl Display for RuntimeError {
             self.token.line(),
             self.token.kind(),
             self.message
-        )
+        )?;
+        write_backtrace(f, &self.backtrace)
+    }
+}
+
+/// An error raised by a native function. It has no token of its own, so the
+/// interpreter reports it at the call site as a `RuntimeError`.
+#[derive(Debug)]
+pub struct NativeError {
+    message: String,
+}
+
+impl NativeError {
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl Error for NativeError {}
+
+impl Display for NativeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Raised by the `exit` native to unwind the whole program. It is never caught by
+/// `try`/`catch`; `Lox` turns it into the process exit code.
+#[derive(Debug)]
+pub struct ExitError {
+    code: u8,
+}
+
+impl ExitError {
+    pub fn new(code: u8) -> Self {
+        Self { code }
+    }
+
+    pub fn code(&self) -> u8 {
+        self.code
+    }
+}
+
+impl Error for ExitError {}
+
+impl Display for ExitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Exited with code {}", self.code)
     }
 }
 
@@ -351,60 +824,599 @@ impl ReturnError {
     pub fn new(value: LoxValue) -> Self {
         Self { value }
     }
-}
-
-impl Error for ReturnError {}
+}
+
+impl Error for ReturnError {}
+
+impl Display for ReturnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+#[derive(Debug)]
+struct ThrowError {
+    token: Token,
+    value: LoxValue,
+    backtrace: Vec<String>,
+}
+
+impl ThrowError {
+    pub fn new(token: Token, value: LoxValue) -> Self {
+        Self {
+            token,
+            value,
+            backtrace: Vec::new(),
+        }
+    }
+}
+
+impl Error for ThrowError {}
+
+impl Display for ThrowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Line {} at '{}': Uncaught exception: {}",
+            self.token.line(),
+            self.token.kind(),
+            self.value
+        )?;
+        write_backtrace(f, &self.backtrace)
+    }
+}
+
+fn write_backtrace(f: &mut std::fmt::Formatter<'_>, backtrace: &[String]) -> std::fmt::Result {
+    for entry in backtrace {
+        write!(f, "\n    in {}", entry)?;
+    }
+
+    Ok(())
+}
+
+/// Records that `err` passed through `entry` on its way out, if it is an error that
+/// is reported to the user.
+fn add_backtrace(mut err: Box<dyn Error>, entry: String) -> Box<dyn Error> {
+    if let Some(runtime_err) = err.downcast_mut::<RuntimeError>() {
+        runtime_err.backtrace.push(entry);
+    } else if let Some(throw_err) = err.downcast_mut::<ThrowError>() {
+        throw_err.backtrace.push(entry);
+    }
+
+    err
+}
+
+/// Returns the value a `catch` clause binds for `err`, or `None` if the error is
+/// control flow (such as `return`) that Lox code must not intercept. Runtime errors
+/// become instances of `error_class`.
+fn exception_value(err: &(dyn Error + 'static), error_class: &Rc<Class>) -> Option<LoxValue> {
+    if let Some(throw_err) = err.downcast_ref::<ThrowError>() {
+        return Some(throw_err.value.clone());
+    }
+    if let Some(runtime_err) = err.downcast_ref::<RuntimeError>() {
+        return Some(runtime_err.to_lox_value(error_class.clone()));
+    }
+
+    None
+}
+
+/// Whether `err` stops the whole program, so that no `finally` block can replace it.
+fn ends_program(err: &(dyn Error + 'static)) -> bool {
+    err.is::<ExitError>() || err.is::<LimitError>()
+}
+
+#[derive(Clone)]
+pub struct Interpreter {
+    environment: Rc<RefCell<Environment>>,
+    globals: Rc<RefCell<Environment>>,
+    locals: HashMap<Expr, usize>,
+    random: Random,
+    filesystem_access: bool,
+    budget: Budget,
+    capabilities: HashSet<Capability>,
+    clock: Clock,
+    // The class of the errors the interpreter raises, also the global `Error`
+    error_class: Rc<Class>,
+}
+
+impl Default for Interpreter {
+    /// An interpreter with the whole standard library. Use `InterpreterBuilder` to
+    /// choose which natives a script can see.
+    fn default() -> Self {
+        InterpreterBuilder::new().allow_all().build()
+    }
+}
+
+/// Builds an interpreter whose globals hold only the native groups the host allows.
+/// Nothing is allowed by default, so untrusted code can be given `math` and `str`
+/// without also getting `readFile` or `exit`:
+///
+/// ```
+/// use rlox::{interpreter::InterpreterBuilder, stdlib::Capability};
+///
+/// let interpreter = InterpreterBuilder::new()
+///     .allow(Capability::Math)
+///     .allow(Capability::String)
+///     .build();
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct InterpreterBuilder {
+    capabilities: HashSet<Capability>,
+}
+
+impl InterpreterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow(mut self, capability: Capability) -> Self {
+        self.capabilities.insert(capability);
+        self
+    }
+
+    pub fn allow_all(mut self) -> Self {
+        self.capabilities.extend(Capability::ALL);
+        self
+    }
+
+    pub fn build(self) -> Interpreter {
+        let mut globals = Environment::default();
+        for capability in &self.capabilities {
+            capability.define(&mut globals);
+        }
+
+        // Part of the language rather than a native group, so it is always defined
+        let error_class = gc::class(Class::new(
+            "Error".into(),
+            None,
+            HashMap::new(),
+            HashMap::new(),
+        ));
+        globals.define("Error", LoxValue::Class(error_class.clone()));
+
+        let environment = gc::environment(globals);
+
+        Interpreter {
+            environment: environment.clone(),
+            globals: environment.clone(),
+            locals: HashMap::new(),
+            random: Random::from_time(),
+            filesystem_access: true,
+            budget: Budget::default(),
+            capabilities: self.capabilities,
+            clock: Clock::System,
+            error_class,
+        }
+    }
+}
+
+impl Interpreter {
+    pub fn interpret(&mut self, statements: Vec<Stmt>) -> Result<(), Box<dyn Error>> {
+        self.budget.start();
+
+        for statement in statements {
+            statement.accept(self)?;
+        }
+
+        Ok(())
+    }
+
+    /// Converts a value to the text that `print` and string interpolation show. Instances
+    /// whose class has a `toString()` method taking no arguments are converted by calling it,
+    /// including instances inside lists and maps.
+    pub fn stringify(&mut self, value: LoxValue) -> Result<String, Box<dyn Error>> {
+        let mut out = String::new();
+        self.stringify_into(&value, &mut out, &mut Vec::new())?;
+
+        Ok(out)
+    }
+
+    /// Appends the text of `value` to `out`. `containers` holds the lists and maps
+    /// currently being converted, which print as `[...]` and `{...}` when they repeat,
+    /// as in `Display`.
+    fn stringify_into(
+        &mut self,
+        value: &LoxValue,
+        out: &mut String,
+        containers: &mut Vec<*const ()>,
+    ) -> Result<(), Box<dyn Error>> {
+        match value {
+            LoxValue::Instance(instance) => {
+                let method = instance.borrow().class.find_method("toString").cloned();
+                match method.filter(|method| method.arity().contains(&0)) {
+                    Some(method) => match method.bind(instance.clone()) {
+                        LoxValue::Function(fun) => {
+                            out.push_str(&fun.call(self, Vec::new())?.to_string())
+                        }
+                        _ => out.push_str(&value.to_string()),
+                    },
+                    None => out.push_str(&value.to_string()),
+                }
+            }
+            LoxValue::List(list) => {
+                let container = Rc::as_ptr(list) as *const ();
+                if containers.contains(&container) {
+                    out.push_str("[...]");
+                    return Ok(());
+                }
+                containers.push(container);
+                out.push('[');
+                // toString() may change the list, so each item is looked up afresh and
+                // the list isn't borrowed while it runs
+                for i in 0.. {
+                    let item = list.borrow().get(i).cloned();
+                    let Some(item) = item else {
+                        break;
+                    };
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    self.stringify_into(&item, out, containers)?;
+                }
+                containers.pop();
+                out.push(']');
+            }
+            LoxValue::Map(map) => {
+                let container = Rc::as_ptr(map) as *const ();
+                if containers.contains(&container) {
+                    out.push_str("{...}");
+                    return Ok(());
+                }
+                containers.push(container);
+                out.push('{');
+                let entries: Vec<_> = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect();
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    out.push_str(key);
+                    out.push_str(": ");
+                    self.stringify_into(value, out, containers)?;
+                }
+                containers.pop();
+                out.push('}');
+            }
+            _ => out.push_str(&value.to_string()),
+        }
+
+        Ok(())
+    }
+
+    /// Allows or denies the file natives (`readFile`, `writeFile`, ...) access to the
+    /// filesystem. Access is allowed by default; when denied, those natives raise a
+    /// runtime error instead.
+    pub fn set_filesystem_access(&mut self, allowed: bool) {
+        self.filesystem_access = allowed;
+    }
+
+    pub fn filesystem_access(&self) -> bool {
+        self.filesystem_access
+    }
+
+    /// Limits each run to `steps` loop iterations and function calls, or removes the
+    /// limit with `None`.
+    pub fn set_step_limit(&mut self, steps: Option<u64>) {
+        self.budget.set_step_limit(steps);
+    }
+
+    /// Limits the wall-clock time of each run, or removes the limit with `None`.
+    pub fn set_time_limit(&mut self, time: Option<Duration>) {
+        self.budget.set_time_limit(time);
+    }
+
+    /// Caps the estimated memory used by the script's values, or removes the cap with
+    /// `None`. Going over it raises an out-of-memory runtime error, which scripts can
+    /// catch. The estimate covers every interpreter on the thread.
+    pub fn set_memory_limit(&mut self, bytes: Option<usize>) {
+        self.budget.set_memory_limit(bytes);
+    }
+
+    /// Limits how deeply Lox function calls may nest, `DEFAULT_DEPTH_LIMIT` unless set.
+    /// Each call uses the host thread's stack, so a thread with a small stack may need a
+    /// lower limit, and a higher one needs a thread with a bigger stack.
+    pub fn set_depth_limit(&mut self, depth: usize) {
+        self.budget.set_depth_limit(depth);
+    }
+
+    /// A handle that stops the running script when cancelled. It can be sent to
+    /// another thread, and cancellation lasts for the life of the interpreter.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.budget.cancel_handle()
+    }
+
+    /// Whether the script can see the natives in `capability`.
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    /// Exposes the script's command-line arguments as the global `args` list, if
+    /// the `process` natives are allowed.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        if !self.allows(Capability::Process) {
+            return;
+        }
+
+        self.globals
+            .borrow_mut()
+            .define("args".to_string(), stdlib::process::args(args));
+    }
+
+    pub fn random(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Makes runs reproducible: `clock` reads a virtual clock that stays at zero
+    /// until the host moves it through the returned handle, and the random natives
+    /// start from `seed`.
+    pub fn set_deterministic(&mut self, seed: u64) -> VirtualClock {
+        let clock = VirtualClock::default();
+        self.clock = Clock::Virtual(clock.clone());
+        self.random = Random::new(seed);
+
+        clock
+    }
+
+    fn execute_block(
+        &mut self,
+        statements: &Vec<Stmt>,
+        environment: Environment,
+    ) -> Result<(), Box<dyn Error>> {
+        let previous = self.environment.clone();
+        self.environment = gc::environment(environment);
+
+        for statement in statements {
+            match statement.accept(self) {
+                Ok(_) => {}
+                Err(err) => {
+                    self.environment = previous;
+                    return Err(err);
+                }
+            }
+        }
+
+        self.environment = previous;
+        Ok(())
+    }
+
+    /// Fails if `bytes` more would take the script over its memory limit. The estimate
+    /// is only corrected by a collection, so one runs before giving up. Natives call
+    /// this with the size of a string or list before building it, so that a single
+    /// large result can't get past the limit.
+    pub fn reserve(&mut self, bytes: usize) -> Result<(), Box<dyn Error>> {
+        self.check_memory(bytes)?;
+        gc::charge(bytes);
+
+        Ok(())
+    }
+
+    /// Like `reserve`, but without counting the bytes as used. Natives that build a large
+    /// result bit by bit call this as it grows, and `reserve` its size once it's done.
+    pub fn check_memory(&mut self, bytes: usize) -> Result<(), Box<dyn Error>> {
+        let Some(limit) = self.budget.memory_limit() else {
+            return Ok(());
+        };
+
+        if gc::usage().saturating_add(bytes) > limit {
+            gc::collect();
+            if gc::usage().saturating_add(bytes) > limit {
+                return Err(Box::new(NativeError::new(format!(
+                    "Out of memory: the script needs more than {} bytes",
+                    limit
+                ))));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Like `reserve`, but reports running out of memory at `token`.
+    fn reserve_memory(&mut self, token: &Token, bytes: usize) -> Result<(), Box<dyn Error>> {
+        self.reserve(bytes).map_err(|err| -> Box<dyn Error> {
+            Box::new(RuntimeError::new(token.clone(), err.to_string()))
+        })
+    }
+
+    pub fn resolve(&mut self, expr: &Expr, depth: usize) {
+        self.locals.insert(expr.clone(), depth);
+    }
+
+    fn binary_operation(
+        &mut self,
+        operator: &Token,
+        left: LoxValue,
+        right: LoxValue,
+    ) -> Result<LoxValue, Box<dyn Error>> {
+        if let LoxValue::Instance(instance) = &left {
+            if let Some(method) = operator_method(operator.kind()) {
+                let result = self.call_operator_method(
+                    operator,
+                    instance.clone(),
+                    method,
+                    vec![right.clone()],
+                )?;
+                match (result, operator.kind()) {
+                    (Some(result), TokenKind::BangEqual) => {
+                        return Ok(LoxValue::Boolean(!result.is_truthy()))
+                    }
+                    (Some(result), _) => return Ok(result),
+                    // Without __eq, instances keep the default equality
+                    (None, TokenKind::EqualEqual | TokenKind::BangEqual) => {}
+                    (None, _) => {
+                        return Err(undefined_operator(operator, &instance.borrow(), method))
+                    }
+                }
+            }
+        }
+
+        if let LoxValue::Instance(instance) = &right {
+            if let Some(method) = operator_method(operator.kind()) {
+                match operator.kind() {
+                    // Equality is symmetric, so `5 == v` asks v's __eq just as `v == 5` does
+                    TokenKind::EqualEqual | TokenKind::BangEqual => {
+                        let result = self.call_operator_method(
+                            operator,
+                            instance.clone(),
+                            method,
+                            vec![left.clone()],
+                        )?;
+                        if let Some(result) = result {
+                            let equal = result.is_truthy();
+                            return Ok(LoxValue::Boolean(
+                                equal == (operator.kind() == &TokenKind::EqualEqual),
+                            ));
+                        }
+                    }
+                    // Other operators aren't symmetric (`3 - v` isn't `v - 3`), so only
+                    // the left operand's method is called
+                    _ => {
+                        return Err(Box::new(RuntimeError::new(
+                            operator.clone(),
+                            format!(
+                                "Can't use '{}' with {} instance on the right; '{}' is only called on the left operand",
+                                operator.kind(),
+                                instance.borrow().class.name,
+                                method
+                            ),
+                        )))
+                    }
+                }
+            }
+        }
+
+        match operator.kind() {
+            TokenKind::Minus => evaluate_number_operands(operator.clone(), left, right, |x, y| {
+                LoxValue::Number(x - y)
+            }),
+            // Division follows IEEE 754 and produces infinity or NaN, but a remainder by
+            // zero has no sensible value, so it is an error
+            TokenKind::Percent
+                if matches!(left, LoxValue::Number(_)) && right == LoxValue::Number(0.0) =>
+            {
+                Err(Box::new(RuntimeError::new(
+                    operator.clone(),
+                    "Modulo by zero".to_string(),
+                )))
+            }
+            TokenKind::Slash => evaluate_number_operands(operator.clone(), left, right, |x, y| {
+                LoxValue::Number(x / y)
+            }),
+            TokenKind::Star => evaluate_number_operands(operator.clone(), left, right, |x, y| {
+                LoxValue::Number(x * y)
+            }),
+            // The remainder takes the sign of the dividend, so -7 % 3 is -1
+            TokenKind::Percent => {
+                evaluate_number_operands(operator.clone(), left, right, |x, y| {
+                    LoxValue::Number(x % y)
+                })
+            }
+            TokenKind::StarStar => {
+                evaluate_number_operands(operator.clone(), left, right, |x, y| {
+                    LoxValue::Number(x.powf(y))
+                })
+            }
+            TokenKind::Plus => match (left, right) {
+                (LoxValue::Number(x), LoxValue::Number(y)) => Ok(LoxValue::Number(x + y)),
+                (LoxValue::String(x), LoxValue::String(y)) => {
+                    self.reserve_memory(operator, x.len() + y.len())?;
+                    Ok(LoxValue::String(format!("{}{}", x, y).into()))
+                }
+                _ => Err(Box::new(RuntimeError::new(
+                    operator.clone(),
+                    "Expected two numbers or two strings".to_string(),
+                ))),
+            },
+            TokenKind::Greater => {
+                evaluate_number_operands(operator.clone(), left, right, |x, y| {
+                    LoxValue::Boolean(x > y)
+                })
+            }
+            TokenKind::GreaterEqual => {
+                evaluate_number_operands(operator.clone(), left, right, |x, y| {
+                    LoxValue::Boolean(x >= y)
+                })
+            }
+            TokenKind::Less => evaluate_number_operands(operator.clone(), left, right, |x, y| {
+                LoxValue::Boolean(x < y)
+            }),
+            TokenKind::LessEqual => {
+                evaluate_number_operands(operator.clone(), left, right, |x, y| {
+                    LoxValue::Boolean(x <= y)
+                })
+            }
+            TokenKind::BangEqual => Ok(LoxValue::Boolean(left != right)),
+            TokenKind::EqualEqual => Ok(LoxValue::Boolean(left == right)),
+            _ => Err(Box::new(RuntimeError::new(
+                operator.clone(),
+                "Expected binary operator".to_string(),
+            ))),
+        }
+    }
 
-impl Display for ReturnError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.value)
+    /// Runs a getter that property access found, so the property reads as its result.
+    /// Any other value is returned as it is.
+    fn resolve_property(&mut self, value: LoxValue) -> Result<LoxValue, Box<dyn Error>> {
+        match value {
+            LoxValue::Function(fun) if fun.is_getter() => fun.call(self, Vec::new()),
+            value => Ok(value),
+        }
     }
-}
-
-#[derive(Clone)]
-pub struct Interpreter {
-    environment: Rc<RefCell<Environment>>,
-    globals: Rc<RefCell<Environment>>,
-    locals: HashMap<Expr, usize>,
-}
-
-impl Default for Interpreter {
-    fn default() -> Self {
-        let mut globals = Environment::default();
-
-        globals.define(
-            "clock".to_string(),
-            LoxValue::NativeFunction(NativeFunction::new(Rc::new(|| {
-                LoxValue::Number(
-                    SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs_f64(),
-                )
-            }))),
-        );
 
-        let environment = Rc::new(RefCell::new(globals));
+    /// Calls the special method that overloads an operator for `instance`, or returns
+    /// `None` if its class doesn't define one.
+    fn call_operator_method(
+        &mut self,
+        operator: &Token,
+        instance: Rc<RefCell<Instance>>,
+        method: &str,
+        arguments: Vec<LoxValue>,
+    ) -> Result<Option<LoxValue>, Box<dyn Error>> {
+        let function = instance.borrow().class.find_method(method).cloned();
+        let function = match function {
+            Some(function) => function,
+            None => return Ok(None),
+        };
 
-        Self {
-            environment: environment.clone(),
-            globals: environment.clone(),
-            locals: HashMap::new(),
+        if !function.arity().contains(&arguments.len()) {
+            return Err(Box::new(RuntimeError::new(
+                operator.clone(),
+                format!(
+                    "Expected '{}' to take {} arguments but it takes {}",
+                    method,
+                    arguments.len(),
+                    function.arity().start()
+                ),
+            )));
         }
-    }
-}
 
-impl Interpreter {
-    pub fn interpret(&mut self, statements: Vec<Stmt>) -> Result<(), Box<dyn Error>> {
-        for statement in statements {
-            statement.accept(self)?;
+        match function.bind(instance) {
+            LoxValue::Function(bound) => Ok(Some(bound.call(self, arguments)?)),
+            _ => Ok(None),
         }
-
-        Ok(())
     }
 
-    pub fn resolve(&mut self, expr: &Expr, depth: usize) {
-        self.locals.insert(expr.clone(), depth);
+    fn assign_variable(
+        &mut self,
+        name: &Token,
+        expr: &Expr,
+        value: LoxValue,
+    ) -> Result<LoxValue, Box<dyn Error>> {
+        let distance = self.locals.get(expr);
+
+        match distance {
+            Some(distance) => self
+                .environment
+                .borrow_mut()
+                .assign_at(*distance, name, value),
+            None => self.globals.borrow_mut().assign(name, value),
+        }
     }
 
     fn look_up_variable(&mut self, name: &Token, expr: &Expr) -> Result<LoxValue, Box<dyn Error>> {
@@ -417,6 +1429,56 @@ impl Interpreter {
     }
 }
 
+/// The special method a class defines to overload a binary operator.
+fn operator_method(operator: &TokenKind) -> Option<&'static str> {
+    let method = match operator {
+        TokenKind::Plus => "__add",
+        TokenKind::Minus => "__sub",
+        TokenKind::Star => "__mul",
+        TokenKind::Slash => "__div",
+        TokenKind::Percent => "__mod",
+        TokenKind::StarStar => "__pow",
+        TokenKind::Less => "__lt",
+        TokenKind::LessEqual => "__le",
+        TokenKind::Greater => "__gt",
+        TokenKind::GreaterEqual => "__ge",
+        TokenKind::EqualEqual | TokenKind::BangEqual => "__eq",
+        _ => return None,
+    };
+
+    Some(method)
+}
+
+fn undefined_operator(token: &Token, instance: &Instance, method: &str) -> Box<dyn Error> {
+    Box::new(RuntimeError::new(
+        token.clone(),
+        format!(
+            "Can't use '{}' on {} instance; define '{}' to overload it",
+            token.kind(),
+            instance.class.name,
+            method
+        ),
+    ))
+}
+
+fn undefined_subscript(token: &Token, instance: &Instance, method: &str) -> Box<dyn Error> {
+    Box::new(RuntimeError::new(
+        token.clone(),
+        format!(
+            "Can't index {} instance; define '{}' to overload it",
+            instance.class.name, method
+        ),
+    ))
+}
+
+/// Reports an error raised by a native function at the token that triggered it.
+fn native_error_at(token: &Token, err: Box<dyn Error>) -> Box<dyn Error> {
+    match err.downcast::<NativeError>() {
+        Ok(native_err) => Box::new(RuntimeError::new(token.clone(), native_err.message)),
+        Err(err) => err,
+    }
+}
+
 fn evaluate_number_operands<F: Fn(f64, f64) -> LoxValue>(
     operator: Token,
     left: LoxValue,
@@ -440,7 +1502,7 @@ impl ExprVisitor for Interpreter {
             TokenKind::Nil => Ok(LoxValue::Nil),
             TokenKind::True => Ok(LoxValue::Boolean(true)),
             TokenKind::False => Ok(LoxValue::Boolean(false)),
-            TokenKind::Number(x) => Ok(LoxValue::Number(x.clone())),
+            TokenKind::Number(x) => Ok(LoxValue::Number(*x)),
             TokenKind::String(s) => Ok(LoxValue::String(s.clone())),
             _ => Err(Box::new(RuntimeError::new(
                 literal.value.clone(),
@@ -459,6 +1521,21 @@ impl ExprVisitor for Interpreter {
         match unary.operator.kind() {
             TokenKind::Minus => match right {
                 LoxValue::Number(x) => Ok(LoxValue::Number(-x)),
+                LoxValue::Instance(instance) => {
+                    match self.call_operator_method(
+                        &unary.operator,
+                        instance.clone(),
+                        "__neg",
+                        Vec::new(),
+                    )? {
+                        Some(result) => Ok(result),
+                        None => Err(undefined_operator(
+                            &unary.operator,
+                            &instance.borrow(),
+                            "__neg",
+                        )),
+                    }
+                }
                 _ => Err(Box::new(RuntimeError::new(
                     unary.operator.clone(),
                     "Expected number after unary operator".to_string(),
@@ -476,57 +1553,7 @@ impl ExprVisitor for Interpreter {
         let left = binary.left.accept(self)?;
         let right = binary.right.accept(self)?;
 
-        match binary.operator.kind() {
-            TokenKind::Minus => {
-                evaluate_number_operands(binary.operator.clone(), left, right, |x, y| {
-                    LoxValue::Number(x - y)
-                })
-            }
-            TokenKind::Slash => {
-                evaluate_number_operands(binary.operator.clone(), left, right, |x, y| {
-                    LoxValue::Number(x / y)
-                })
-            }
-            TokenKind::Star => {
-                evaluate_number_operands(binary.operator.clone(), left, right, |x, y| {
-                    LoxValue::Number(x * y)
-                })
-            }
-            TokenKind::Plus => match (left, right) {
-                (LoxValue::Number(x), LoxValue::Number(y)) => Ok(LoxValue::Number(x + y)),
-                (LoxValue::String(x), LoxValue::String(y)) => Ok(LoxValue::String(x + &y)),
-                _ => Err(Box::new(RuntimeError::new(
-                    binary.operator.clone(),
-                    "Expected two numbers or two strings".to_string(),
-                ))),
-            },
-            TokenKind::Greater => {
-                evaluate_number_operands(binary.operator.clone(), left, right, |x, y| {
-                    LoxValue::Boolean(x > y)
-                })
-            }
-            TokenKind::GreaterEqual => {
-                evaluate_number_operands(binary.operator.clone(), left, right, |x, y| {
-                    LoxValue::Boolean(x >= y)
-                })
-            }
-            TokenKind::Less => {
-                evaluate_number_operands(binary.operator.clone(), left, right, |x, y| {
-                    LoxValue::Boolean(x < y)
-                })
-            }
-            TokenKind::LessEqual => {
-                evaluate_number_operands(binary.operator.clone(), left, right, |x, y| {
-                    LoxValue::Boolean(x <= y)
-                })
-            }
-            TokenKind::BangEqual => Ok(LoxValue::Boolean(left != right)),
-            TokenKind::EqualEqual => Ok(LoxValue::Boolean(left == right)),
-            _ => Err(Box::new(RuntimeError::new(
-                binary.operator.clone(),
-                "Expected binary operator".to_string(),
-            ))),
-        }
+        self.binary_operation(&binary.operator, left, right)
     }
 
     fn visit_variable(&mut self, variable: &crate::ast::Variable) -> Self::Result {
@@ -540,15 +1567,7 @@ impl ExprVisitor for Interpreter {
     fn visit_assign(&mut self, assign: &crate::ast::Assign) -> Self::Result {
         let value = assign.value.accept(self)?;
 
-        let distance = self.locals.get(&Expr::Assign(assign.clone()));
-        match distance {
-            Some(distance) => {
-                self.environment
-                    .borrow_mut()
-                    .assign_at(*distance, &assign.name, value)
-            }
-            None => self.globals.borrow_mut().assign(&assign.name, value),
-        }
+        self.assign_variable(&assign.name, &Expr::Assign(assign.clone()), value)
     }
 
     fn visit_logical(&mut self, logical: &crate::ast::Logical) -> Self::Result {
@@ -584,6 +1603,13 @@ impl ExprVisitor for Interpreter {
             arguments.push(argument.accept(self)?);
         }
 
+        let constructor = match &callee {
+            LoxValue::Class(class) => Some(class.name.clone()),
+            _ => None,
+        };
+
+        self.reserve_memory(&call.paren, 0)?;
+
         let function: Box<dyn LoxCallable> = match callee {
             LoxValue::NativeFunction(nfun) => Box::new(nfun),
             LoxValue::Function(fun) => Box::new(fun),
@@ -596,27 +1622,55 @@ impl ExprVisitor for Interpreter {
             }
         };
 
-        if arguments.len() != function.arity() {
+        let arity = function.arity();
+        if !arity.contains(&arguments.len()) {
+            let expected = if arity.start() == arity.end() {
+                arity.start().to_string()
+            } else if *arity.end() == usize::MAX {
+                format!("at least {}", arity.start())
+            } else {
+                format!("{} to {}", arity.start(), arity.end())
+            };
+
             return Err(Box::new(RuntimeError::new(
                 call.paren.clone(),
                 format!(
                     "Expected {} arguments but got {}",
-                    function.arity(),
+                    expected,
                     arguments.len()
                 ),
             )));
         }
 
-        function.call(self, arguments)
+        function.call(self, arguments).map_err(|err| {
+            let err = native_error_at(&call.paren, err);
+            match &constructor {
+                Some(name) => add_backtrace(
+                    err,
+                    format!("{} constructor called at line {}", name, call.paren.line()),
+                ),
+                None => err,
+            }
+        })
     }
 
     fn visit_get(&mut self, get: &crate::ast::Get) -> Self::Result {
         let object = get.object.accept(self)?;
         match object {
-            LoxValue::Instance(instance) => instance.borrow_mut().get(&get.name, instance.clone()),
+            LoxValue::Instance(instance) => {
+                let value = instance.borrow().get(&get.name, instance.clone())?;
+                self.resolve_property(value)
+            }
+            LoxValue::Class(class) => {
+                let value = class.get(&get.name)?;
+                self.resolve_property(value)
+            }
+            LoxValue::String(string) => stdlib::string::method(&get.name, string),
+            LoxValue::List(list) => stdlib::list::method(&get.name, list),
+            LoxValue::Map(map) => stdlib::map::method(&get.name, map),
             _ => Err(Box::new(RuntimeError::new(
                 get.name.clone(),
-                "Only instances have properties".to_string(),
+                "Only instances, classes, strings, lists and maps have properties".to_string(),
             ))),
         }
     }
@@ -626,65 +1680,250 @@ impl ExprVisitor for Interpreter {
         match object {
             LoxValue::Instance(instance) => {
                 let value = set.value.accept(self)?;
-                instance.borrow_mut().set(&set.name, value.clone());
+                instance.borrow_mut().set(&set.name, value.clone())?;
+                Ok(value)
+            }
+            LoxValue::Class(class) => {
+                let value = set.value.accept(self)?;
+                class.set(&set.name, value.clone());
                 Ok(value)
             }
             _ => Err(Box::new(RuntimeError::new(
                 set.name.clone(),
-                "Only instances have fields".to_string(),
+                "Only instances and classes have fields".to_string(),
+            ))),
+        }
+    }
+
+    fn visit_index(&mut self, index: &crate::ast::Index) -> Self::Result {
+        let object = index.object.accept(self)?;
+        let key = index.index.accept(self)?;
+
+        let result = match object {
+            LoxValue::List(list) => {
+                stdlib::index_argument("[]", &key).and_then(|i| stdlib::list::get(&list, i))
+            }
+            LoxValue::String(string) => {
+                stdlib::index_argument("[]", &key).and_then(|i| stdlib::string::char_at(&string, i))
+            }
+            LoxValue::Map(map) => stdlib::string_argument("[]", &key)
+                .map(|key| map.borrow().get(&key).cloned().unwrap_or(LoxValue::Nil)),
+            LoxValue::Instance(instance) => {
+                return match self.call_operator_method(
+                    &index.bracket,
+                    instance.clone(),
+                    "__index",
+                    vec![key],
+                )? {
+                    Some(value) => Ok(value),
+                    None => Err(undefined_subscript(
+                        &index.bracket,
+                        &instance.borrow(),
+                        "__index",
+                    )),
+                }
+            }
+            _ => {
+                return Err(Box::new(RuntimeError::new(
+                    index.bracket.clone(),
+                    "Only lists, strings, maps and instances can be indexed".to_string(),
+                )))
+            }
+        };
+
+        result.map_err(|err| native_error_at(&index.bracket, err))
+    }
+
+    fn visit_setindex(&mut self, setindex: &crate::ast::SetIndex) -> Self::Result {
+        let object = setindex.object.accept(self)?;
+        let key = setindex.index.accept(self)?;
+        let value = setindex.value.accept(self)?;
+
+        let result =
+            match object {
+                LoxValue::List(list) => stdlib::index_argument("[]", &key)
+                    .and_then(|i| stdlib::list::set(&list, i, value)),
+                LoxValue::Map(map) => stdlib::string_argument("[]", &key)
+                    .map(|key| stdlib::map::set(&map, key, value)),
+                LoxValue::Instance(instance) => {
+                    return match self.call_operator_method(
+                        &setindex.bracket,
+                        instance.clone(),
+                        "__setindex",
+                        vec![key, value.clone()],
+                    )? {
+                        Some(_) => Ok(value),
+                        None => Err(undefined_subscript(
+                            &setindex.bracket,
+                            &instance.borrow(),
+                            "__setindex",
+                        )),
+                    }
+                }
+                _ => {
+                    return Err(Box::new(RuntimeError::new(
+                        setindex.bracket.clone(),
+                        "Only lists, maps and instances support index assignment".to_string(),
+                    )))
+                }
+            };
+
+        result.map_err(|err| native_error_at(&setindex.bracket, err))
+    }
+
+    fn visit_stringify(&mut self, stringify: &crate::ast::Stringify) -> Self::Result {
+        let value = stringify.expression.accept(self)?;
+        let string = self.stringify(value)?;
+        self.reserve_memory(&stringify.token, string.len())?;
+
+        Ok(LoxValue::String(string.into()))
+    }
+
+    fn visit_conditional(&mut self, conditional: &crate::ast::Conditional) -> Self::Result {
+        if conditional.condition.accept(self)?.is_truthy() {
+            conditional.then_branch.accept(self)
+        } else {
+            conditional.else_branch.accept(self)
+        }
+    }
+
+    fn visit_compoundset(&mut self, compoundset: &crate::ast::CompoundSet) -> Self::Result {
+        let object = compoundset.object.accept(self)?;
+        match object {
+            LoxValue::Instance(instance) => {
+                let current = instance.borrow().get(&compoundset.name, instance.clone())?;
+                let current = self.resolve_property(current)?;
+                let value = compoundset.value.accept(self)?;
+                let value = self.binary_operation(&compoundset.operator, current, value)?;
+                instance
+                    .borrow_mut()
+                    .set(&compoundset.name, value.clone())?;
+                Ok(value)
+            }
+            LoxValue::Class(class) => {
+                let current = class.get(&compoundset.name)?;
+                let current = self.resolve_property(current)?;
+                let value = compoundset.value.accept(self)?;
+                let value = self.binary_operation(&compoundset.operator, current, value)?;
+                class.set(&compoundset.name, value.clone());
+                Ok(value)
+            }
+            _ => Err(Box::new(RuntimeError::new(
+                compoundset.name.clone(),
+                "Only instances and classes have fields".to_string(),
+            ))),
+        }
+    }
+
+    fn visit_postfix(&mut self, postfix: &crate::ast::Postfix) -> Self::Result {
+        let operator = Token::new(
+            match postfix.operator.kind() {
+                TokenKind::PlusPlus => TokenKind::Plus,
+                _ => TokenKind::Minus,
+            },
+            *postfix.operator.line(),
+        );
+        let one = LoxValue::Number(1.0);
+
+        match postfix.target.as_ref() {
+            Expr::Variable(variable) => {
+                let current = self.look_up_variable(&variable.name, &postfix.target)?;
+                let value = self.binary_operation(&operator, current.clone(), one)?;
+                self.assign_variable(&variable.name, &postfix.target, value)?;
+                Ok(current)
+            }
+            Expr::Get(get) => match get.object.accept(self)? {
+                LoxValue::Instance(instance) => {
+                    let current = instance.borrow().get(&get.name, instance.clone())?;
+                    let current = self.resolve_property(current)?;
+                    let value = self.binary_operation(&operator, current.clone(), one)?;
+                    instance.borrow_mut().set(&get.name, value)?;
+                    Ok(current)
+                }
+                LoxValue::Class(class) => {
+                    let current = class.get(&get.name)?;
+                    let current = self.resolve_property(current)?;
+                    let value = self.binary_operation(&operator, current.clone(), one)?;
+                    class.set(&get.name, value);
+                    Ok(current)
+                }
+                _ => Err(Box::new(RuntimeError::new(
+                    get.name.clone(),
+                    "Only instances and classes have fields".to_string(),
+                ))),
+            },
+            _ => Err(Box::new(RuntimeError::new(
+                postfix.operator.clone(),
+                "Invalid increment target".to_string(),
             ))),
         }
     }
 
     fn visit_this(&mut self, this: &crate::ast::This) -> Self::Result {
         self.look_up_variable(
-            &Token::new(
-                TokenKind::Identifier("this".to_string()),
-                this.keyword.line().clone(),
-            ),
+            &Token::new(TokenKind::Identifier(intern("this")), *this.keyword.line()),
             &Expr::This(this.clone()),
         )
     }
 
     fn visit_superexpr(&mut self, superexpr: &crate::ast::SuperExpr) -> Self::Result {
-        let distance = self
+        // The resolver rejects 'super' outside subclass methods, but an embedder can run
+        // statements it never resolved, so that is an error here rather than a panic
+        let misplaced = || -> Box<dyn Error> {
+            Box::new(RuntimeError::new(
+                superexpr.keyword.clone(),
+                "Can't use 'super' outside of a subclass method".to_string(),
+            ))
+        };
+        let distance = *self
             .locals
             .get(&Expr::SuperExpr(superexpr.clone()))
-            .unwrap();
+            .ok_or_else(misplaced)?;
         let superclass = self.environment.borrow_mut().get_at(
-            distance.clone(),
+            distance,
             &Token::new(
-                TokenKind::Identifier("super".to_string()),
-                superexpr.keyword.line().clone(),
+                TokenKind::Identifier(intern("super")),
+                *superexpr.keyword.line(),
             ),
         )?;
         let object = self.environment.borrow_mut().get_at(
-            distance - 1,
+            distance.checked_sub(1).ok_or_else(misplaced)?,
             &Token::new(
-                TokenKind::Identifier("this".to_string()),
-                superexpr.keyword.line().clone(),
+                TokenKind::Identifier(intern("this")),
+                *superexpr.keyword.line(),
             ),
         )?;
         let method_name = match superexpr.method.kind() {
             TokenKind::Identifier(id) => id.clone(),
-            _ => unreachable!(),
-        };
-        let method = match &superclass {
-            LoxValue::Class(superclass) => superclass.find_method(&method_name),
-            _ => unreachable!(),
-        };
-
-        match method {
-            Some(method) => match object {
-                LoxValue::Instance(object) => Ok(method.bind(object)),
-                _ => unreachable!(),
-            },
-            None => {
+            _ => {
                 return Err(Box::new(RuntimeError::new(
                     superexpr.method.clone(),
-                    format!("Undefined property '{}'.", method_name),
+                    "Expected identifier".to_string(),
                 )))
             }
+        };
+        let superclass = match superclass {
+            LoxValue::Class(superclass) => superclass,
+            _ => return Err(misplaced()),
+        };
+
+        // In a class method, 'this' is the class and 'super' finds class methods
+        let method = match object {
+            LoxValue::Instance(object) => superclass
+                .find_method(&method_name)
+                .map(|method| method.bind(object)),
+            LoxValue::Class(object) => superclass
+                .find_class_method(&method_name)
+                .map(|method| method.bind_class(object)),
+            _ => return Err(misplaced()),
+        };
+
+        match method {
+            Some(method) => self.resolve_property(method),
+            None => Err(Box::new(RuntimeError::new(
+                superexpr.method.clone(),
+                format!("Undefined property '{}'.", method_name),
+            ))),
         }
     }
 }
@@ -693,21 +1932,10 @@ impl StmtVisitor for Interpreter {
     type Result = Result<(), Box<dyn Error>>;
 
     fn visit_block(&mut self, block: &crate::ast::Block) -> Self::Result {
-        let previous = self.environment.clone();
-        self.environment = Rc::new(RefCell::new(Environment::new(previous.clone())));
-
-        for statement in &block.statements {
-            match statement.accept(self) {
-                Ok(_) => {}
-                Err(err) => {
-                    self.environment = previous;
-                    return Err(err);
-                }
-            }
-        }
-
-        self.environment = previous;
-        Ok(())
+        self.execute_block(
+            &block.statements,
+            Environment::new(self.environment.clone()),
+        )
     }
 
     fn visit_expression(&mut self, expression: &crate::ast::Expression) -> Self::Result {
@@ -717,7 +1945,7 @@ impl StmtVisitor for Interpreter {
     fn visit_print(&mut self, print: &crate::ast::Print) -> Self::Result {
         let value = print.expression.accept(self)?;
 
-        println!("{}", value);
+        println!("{}", self.stringify(value)?);
 
         Ok(())
     }
@@ -752,7 +1980,9 @@ impl StmtVisitor for Interpreter {
 
     fn visit_whilestmt(&mut self, whilestmt: &crate::ast::WhileStmt) -> Self::Result {
         while whilestmt.condition.accept(self)?.is_truthy() {
+            self.budget.step()?;
             whilestmt.body.accept(self)?;
+            gc::maybe_collect();
         }
 
         Ok(())
@@ -760,7 +1990,7 @@ impl StmtVisitor for Interpreter {
 
     fn visit_function(&mut self, function: &crate::ast::Function) -> Self::Result {
         let fun = LoxValue::Function(Function::new(
-            function.clone(),
+            Rc::new(function.clone()),
             self.environment.clone(),
             false,
         ));
@@ -796,7 +2026,7 @@ impl StmtVisitor for Interpreter {
                 };
                 let superclass = superclass.accept(self)?;
                 match superclass {
-                    LoxValue::Class(class) => Some(Box::new(class)),
+                    LoxValue::Class(class) => Some(class),
                     _ => {
                         return Err(Box::new(RuntimeError::new(
                             superclass_name,
@@ -822,27 +2052,64 @@ impl StmtVisitor for Interpreter {
             .borrow_mut()
             .define(name.clone(), LoxValue::Nil);
 
+        // Trait methods come first so that the class's own methods override them
+        let mut methods: HashMap<Rc<str>, Function> = HashMap::new();
+        let mut class_methods: HashMap<Rc<str>, Function> = HashMap::new();
+        for trait_expr in &class.traits {
+            match trait_expr.accept(self)? {
+                LoxValue::Trait(mixin) => {
+                    methods.extend(mixin.methods.clone());
+                    class_methods.extend(mixin.class_methods.clone());
+                }
+                _ => {
+                    let name = match trait_expr {
+                        Expr::Variable(var) => var.name.clone(),
+                        _ => class.name.clone(),
+                    };
+                    return Err(Box::new(RuntimeError::new(
+                        name,
+                        "Can only mix in traits.".to_string(),
+                    )));
+                }
+            }
+        }
+
         let enclosing_environment = self.environment.clone();
         if let Some(superclass) = &superclass {
-            self.environment = Rc::new(RefCell::new(Environment::new(self.environment.clone())));
+            self.environment = gc::environment(Environment::new(self.environment.clone()));
             self.environment
                 .borrow_mut()
-                .define("super".to_string(), LoxValue::Class(*superclass.clone()));
+                .define("super".to_string(), LoxValue::Class(superclass.clone()));
         }
 
-        let mut methods: HashMap<String, Function> = HashMap::new();
         for method in &class.methods {
             let is_initializer = match method.name.kind() {
-                TokenKind::Identifier(id) => id == "init",
+                TokenKind::Identifier(id) => &**id == "init",
                 _ => false,
             };
-            let function = Function::new(method.clone(), self.environment.clone(), is_initializer);
+            let function = Function::new(
+                Rc::new(method.clone()),
+                self.environment.clone(),
+                is_initializer,
+            );
             if let TokenKind::Identifier(name) = method.name.kind() {
                 methods.insert(name.clone(), function);
             }
         }
 
-        let klass = LoxValue::Class(Class::new(name.clone(), superclass, methods));
+        for method in &class.class_methods {
+            let function = Function::new(Rc::new(method.clone()), self.environment.clone(), false);
+            if let TokenKind::Identifier(name) = method.name.kind() {
+                class_methods.insert(name.clone(), function);
+            }
+        }
+
+        let klass = LoxValue::Class(gc::class(Class::new(
+            name.clone(),
+            superclass,
+            methods,
+            class_methods,
+        )));
         self.environment.borrow_mut().assign(&class.name, klass)?;
 
         if class.superclass.is_some() {
@@ -851,4 +2118,77 @@ impl StmtVisitor for Interpreter {
 
         Ok(())
     }
+
+    fn visit_traitstmt(&mut self, traitstmt: &crate::ast::TraitStmt) -> Self::Result {
+        let name = match traitstmt.name.kind() {
+            TokenKind::Identifier(id) => id.clone(),
+            _ => {
+                return Err(Box::new(RuntimeError::new(
+                    traitstmt.name.clone(),
+                    "Expected identifier".to_string(),
+                )))
+            }
+        };
+
+        let functions = |methods: &Vec<crate::ast::Function>| {
+            methods
+                .iter()
+                .map(|method| {
+                    let function =
+                        Function::new(Rc::new(method.clone()), self.environment.clone(), false);
+                    (function.name().into(), function)
+                })
+                .collect::<HashMap<Rc<str>, Function>>()
+        };
+        let mixin = Trait::new(
+            name.clone(),
+            functions(&traitstmt.methods),
+            functions(&traitstmt.class_methods),
+        );
+
+        self.environment
+            .borrow_mut()
+            .define(name, LoxValue::Trait(gc::mixin(mixin)));
+
+        Ok(())
+    }
+
+    fn visit_throwstmt(&mut self, throwstmt: &crate::ast::ThrowStmt) -> Self::Result {
+        let value = throwstmt.value.accept(self)?;
+
+        Err(Box::new(ThrowError::new(throwstmt.keyword.clone(), value)))
+    }
+
+    fn visit_trystmt(&mut self, trystmt: &crate::ast::TryStmt) -> Self::Result {
+        let mut result = self.execute_block(
+            &trystmt.try_block,
+            Environment::new(self.environment.clone()),
+        );
+
+        if let (Some(name), Some(catch_block)) = (&trystmt.catch_name, &trystmt.catch_block) {
+            let exception = match &result {
+                Err(err) => exception_value(err.as_ref(), &self.error_class),
+                Ok(_) => None,
+            };
+
+            if let (Some(exception), TokenKind::Identifier(id)) = (exception, name.kind()) {
+                let mut environment = Environment::new(self.environment.clone());
+                environment.define(id.clone(), exception);
+                result = self.execute_block(catch_block, environment);
+            }
+        }
+
+        // The finally block always runs, and an error raised inside it (or a return)
+        // replaces whatever the try or catch block produced, unless the program is
+        // already on its way out.
+        if let Some(finally_block) = &trystmt.finally_block {
+            let finally_result =
+                self.execute_block(finally_block, Environment::new(self.environment.clone()));
+            if !matches!(&result, Err(err) if ends_program(err.as_ref())) {
+                finally_result?;
+            }
+        }
+
+        result
+    }
 }
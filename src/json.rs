@@ -0,0 +1,490 @@
+//! A small JSON reader and writer. It has no knowledge of Lox values, so it can
+//! be shared by the `jsonParse`/`jsonStringify` natives and other tooling.
+
+use std::{
+    collections::BTreeMap, convert::Infallible, error::Error, fmt::Display, iter::Peekable,
+    str::Chars,
+};
+
+/// How deeply arrays and objects may nest before parsing gives up, so hostile input
+/// can't overflow the stack. Writers building `Json` from deeper data should stop at
+/// the same depth.
+pub const MAX_DEPTH: usize = 512;
+
+/// The most spaces `stringify` indents each level by; wider indents are cut down to it,
+/// as in JavaScript.
+pub const MAX_INDENT: usize = 10;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Json {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(BTreeMap<String, Json>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, JsonError> {
+        let mut parser = JsonParser::new(text);
+
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(value),
+            Some(c) => Err(parser.error(format!("unexpected '{}' after the value", c))),
+        }
+    }
+
+    /// Looks up a member of an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Self::Object(members) => members.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Writes the value on a single line, or spread over several lines with each
+    /// level indented by `indent` spaces (at most `MAX_INDENT`).
+    pub fn stringify(&self, indent: Option<usize>) -> String {
+        let Ok(text) = self.try_stringify(indent, |_| Ok::<_, Infallible>(()));
+        text
+    }
+
+    /// Like `stringify`, but calls `check` with the size the text is about to grow to
+    /// whenever it needs more room, so that the caller can stop it growing past a
+    /// memory limit.
+    pub fn try_stringify<E>(
+        &self,
+        indent: Option<usize>,
+        mut check: impl FnMut(usize) -> Result<(), E>,
+    ) -> Result<String, E> {
+        let mut writer = Writer {
+            out: String::new(),
+            indent: indent.map(|indent| indent.min(MAX_INDENT)),
+            check: &mut check,
+        };
+        writer.value(self, 0)?;
+
+        Ok(writer.out)
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.stringify(None))
+    }
+}
+
+struct Writer<'a, E> {
+    out: String,
+    indent: Option<usize>,
+    check: &'a mut dyn FnMut(usize) -> Result<(), E>,
+}
+
+impl<E> Writer<'_, E> {
+    /// Makes room for `additional` more bytes, checking the new size first whenever the
+    /// text has to grow. It grows by doubling, so that happens only a few times.
+    fn grow(&mut self, additional: usize) -> Result<(), E> {
+        // Asking for every byte there is fails under any memory limit
+        let size = self.out.len().saturating_add(additional);
+        if size > self.out.capacity() {
+            (self.check)(size)?;
+            self.out.reserve(additional);
+        }
+
+        Ok(())
+    }
+
+    fn push_str(&mut self, s: &str) -> Result<(), E> {
+        self.grow(s.len())?;
+        self.out.push_str(s);
+
+        Ok(())
+    }
+
+    fn value(&mut self, value: &Json, depth: usize) -> Result<(), E> {
+        match value {
+            Json::Null => self.push_str("null"),
+            Json::Boolean(b) => self.push_str(&b.to_string()),
+            Json::Number(x) => self.push_str(x.to_string().trim_end_matches(".0")),
+            Json::String(s) => self.string(s),
+            Json::Array(items) => {
+                self.push_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        self.push_str(",")?;
+                    }
+                    self.newline(depth + 1)?;
+                    self.value(item, depth + 1)?;
+                }
+                if !items.is_empty() {
+                    self.newline(depth)?;
+                }
+                self.push_str("]")
+            }
+            Json::Object(members) => {
+                self.push_str("{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        self.push_str(",")?;
+                    }
+                    self.newline(depth + 1)?;
+                    self.string(key)?;
+                    self.push_str(if self.indent.is_some() { ": " } else { ":" })?;
+                    self.value(value, depth + 1)?;
+                }
+                if !members.is_empty() {
+                    self.newline(depth)?;
+                }
+                self.push_str("}")
+            }
+        }
+    }
+
+    fn newline(&mut self, depth: usize) -> Result<(), E> {
+        if let Some(indent) = self.indent {
+            let width = indent.saturating_mul(depth);
+            self.grow(width.saturating_add(1))?;
+            self.out.push('\n');
+            self.out.push_str(&" ".repeat(width));
+        }
+
+        Ok(())
+    }
+
+    fn string(&mut self, s: &str) -> Result<(), E> {
+        let escaped = |c: char| match c {
+            '"' | '\\' | '\n' | '\r' | '\t' => 2,
+            c if (c as u32) < 0x20 => 6,
+            c => c.len_utf8(),
+        };
+        self.grow(s.chars().map(escaped).sum::<usize>() + 2)?;
+
+        self.out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                c if (c as u32) < 0x20 => self.out.push_str(&format!("\\u{:04x}", c as u32)),
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct JsonError {
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl Error for JsonError {}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid JSON at line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+struct JsonParser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn error(&self, message: String) -> JsonError {
+        JsonError {
+            line: self.line,
+            column: self.column,
+            message,
+        }
+    }
+
+    fn unexpected(&mut self, expected: &str) -> JsonError {
+        match self.peek() {
+            Some(c) => self.error(format!("expected {} but found '{}'", expected, c)),
+            None => self.error(format!("expected {} but reached the end", expected)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.advance();
+        }
+    }
+
+    fn consume(&mut self, expected: char) -> Result<(), JsonError> {
+        if self.peek() == Some(expected) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{}'", expected)))
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Json, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("too deeply nested".to_string()));
+        }
+
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(depth),
+            Some('[') => self.array(depth),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.literal("true", Json::Boolean(true)),
+            Some('f') => self.literal("false", Json::Boolean(false)),
+            Some('n') => self.literal("null", Json::Null),
+            _ => Err(self.unexpected("a value")),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, JsonError> {
+        for expected in word.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.unexpected(&format!("'{}'", word)));
+            }
+            self.advance();
+        }
+
+        Ok(value)
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Json, JsonError> {
+        self.consume('[')?;
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.advance();
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            items.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.advance();
+                }
+                Some(']') => {
+                    self.advance();
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.unexpected("',' or ']'")),
+            }
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Json, JsonError> {
+        self.consume('{')?;
+        let mut members = BTreeMap::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.advance();
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.unexpected("a string key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.consume(':')?;
+            let value = self.value(depth + 1)?;
+            members.insert(key, value);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.advance();
+                }
+                Some('}') => {
+                    self.advance();
+                    return Ok(Json::Object(members));
+                }
+                _ => return Err(self.unexpected("',' or '}'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.consume('"')?;
+        let mut s = String::new();
+
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(s),
+                Some('\\') => s.push(self.escape()?),
+                Some(c) if (c as u32) < 0x20 => {
+                    return Err(self.error("control character in string".to_string()))
+                }
+                Some(c) => s.push(c),
+                None => return Err(self.error("unterminated string".to_string())),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, JsonError> {
+        let c = match self.advance() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                let high = self.hex_code()?;
+                if !(0xD800..0xDC00).contains(&high) {
+                    return char::from_u32(high)
+                        .ok_or_else(|| self.error("invalid unicode escape".to_string()));
+                }
+
+                // A high surrogate must be followed by an escaped low surrogate
+                if self.advance() != Some('\\') || self.advance() != Some('u') {
+                    return Err(self.error("unpaired surrogate in unicode escape".to_string()));
+                }
+                let low = self.hex_code()?;
+                if !(0xDC00..0xE000).contains(&low) {
+                    return Err(self.error("unpaired surrogate in unicode escape".to_string()));
+                }
+                let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                return char::from_u32(code)
+                    .ok_or_else(|| self.error("invalid unicode escape".to_string()));
+            }
+            Some(c) => return Err(self.error(format!("invalid escape '\\{}'", c))),
+            None => return Err(self.error("unterminated string".to_string())),
+        };
+
+        Ok(c)
+    }
+
+    fn hex_code(&mut self) -> Result<u32, JsonError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .advance()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("expected 4 hex digits in unicode escape".to_string()))?;
+            code = code * 16 + digit;
+        }
+
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let mut text = String::new();
+
+        if self.peek() == Some('-') {
+            text.push('-');
+            self.advance();
+        }
+
+        match self.peek() {
+            Some('0') => {
+                text.push('0');
+                self.advance();
+            }
+            Some('1'..='9') => self.digits(&mut text),
+            _ => return Err(self.unexpected("a digit")),
+        }
+
+        if self.peek() == Some('.') {
+            text.push('.');
+            self.advance();
+            if !matches!(self.peek(), Some('0'..='9')) {
+                return Err(self.unexpected("a digit"));
+            }
+            self.digits(&mut text);
+        }
+
+        if let Some('e' | 'E') = self.peek() {
+            text.push('e');
+            self.advance();
+            if let Some(sign @ ('+' | '-')) = self.peek() {
+                text.push(sign);
+                self.advance();
+            }
+            if !matches!(self.peek(), Some('0'..='9')) {
+                return Err(self.unexpected("a digit"));
+            }
+            self.digits(&mut text);
+        }
+
+        text.parse::<f64>()
+            .map(Json::Number)
+            .map_err(|_| self.error(format!("invalid number '{}'", text)))
+    }
+
+    fn digits(&mut self, text: &mut String) {
+        while let Some(c @ '0'..='9') = self.peek() {
+            text.push(c);
+            self.advance();
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! A tree-walking interpreter for the Lox language. The `rlox` binary is a thin
+//! command-line wrapper around `lox::Lox`; embedders can drive the same types directly.
+
+pub mod ast;
+// pub mod ast_printer;
+pub mod environment;
+pub mod gc;
+pub mod intern;
+pub mod interpreter;
+pub mod json;
+pub mod limits;
+pub mod lox;
+pub mod lsp;
+pub mod parser;
+pub mod resolver;
+pub mod scanner;
+pub mod stdlib;
+pub mod token;
@@ -0,0 +1,183 @@
+//! Execution limits for running untrusted scripts. The interpreter counts a step for
+//! every loop iteration and every call to a Lox function, and stops the script with
+//! a `LimitError` once it runs out of steps, passes its deadline, nests calls too
+//! deeply or is cancelled by the host. `LimitError` isn't a runtime error, so scripts
+//! can't catch it.
+//!
+//! The memory limit is kept here too, but running out of memory is an ordinary
+//! runtime error that scripts can recover from by letting go of values.
+
+use std::{
+    error::Error,
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Lets the host stop a running script from another thread. Cancellation is checked
+/// at the same points as the other limits, so a script stops at its next loop
+/// iteration or call.
+#[derive(Debug, Default, Clone)]
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// How deeply Lox function calls may nest unless the host sets another limit. Every
+/// call uses the host's stack, and this keeps ordinary recursion well clear of the
+/// end of it.
+pub const DEFAULT_DEPTH_LIMIT: usize = 1000;
+
+/// The limit a script ran into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Steps(u64),
+    Time(Duration),
+    Depth(usize),
+    Cancelled,
+}
+
+#[derive(Debug)]
+pub struct LimitError {
+    limit: Limit,
+}
+
+impl LimitError {
+    pub fn limit(&self) -> Limit {
+        self.limit
+    }
+}
+
+impl Error for LimitError {}
+
+impl Display for LimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.limit {
+            Limit::Steps(steps) => write!(
+                f,
+                "Execution limit exceeded: ran for more than {} steps",
+                steps
+            ),
+            Limit::Time(time) => write!(
+                f,
+                "Execution limit exceeded: ran for more than {} seconds",
+                time.as_secs_f64()
+            ),
+            Limit::Depth(depth) => write!(
+                f,
+                "Execution limit exceeded: calls nested more than {} deep",
+                depth
+            ),
+            Limit::Cancelled => write!(f, "Execution limit exceeded: cancelled by the host"),
+        }
+    }
+}
+
+/// The limits configured on an interpreter and its progress against them in the
+/// current run.
+#[derive(Debug, Clone)]
+pub struct Budget {
+    step_limit: Option<u64>,
+    time_limit: Option<Duration>,
+    memory_limit: Option<usize>,
+    depth_limit: usize,
+    steps: u64,
+    deadline: Option<Instant>,
+    depth: usize,
+    cancel: CancelHandle,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self {
+            step_limit: None,
+            time_limit: None,
+            memory_limit: None,
+            depth_limit: DEFAULT_DEPTH_LIMIT,
+            steps: 0,
+            deadline: None,
+            depth: 0,
+            cancel: CancelHandle::default(),
+        }
+    }
+}
+
+impl Budget {
+    pub fn set_step_limit(&mut self, steps: Option<u64>) {
+        self.step_limit = steps;
+    }
+
+    pub fn set_time_limit(&mut self, time: Option<Duration>) {
+        self.time_limit = time;
+    }
+
+    pub fn set_memory_limit(&mut self, bytes: Option<usize>) {
+        self.memory_limit = bytes;
+    }
+
+    pub fn memory_limit(&self) -> Option<usize> {
+        self.memory_limit
+    }
+
+    pub fn set_depth_limit(&mut self, depth: usize) {
+        self.depth_limit = depth;
+    }
+
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Starts counting from zero, with the deadline measured from now.
+    pub fn start(&mut self) {
+        self.steps = 0;
+        self.deadline = self.time_limit.map(|time| Instant::now() + time);
+    }
+
+    /// Counts one step and fails if any limit has been reached.
+    pub fn step(&mut self) -> Result<(), LimitError> {
+        self.steps += 1;
+
+        let limit = if self.cancel.is_cancelled() {
+            Limit::Cancelled
+        } else if self.step_limit.is_some_and(|limit| self.steps > limit) {
+            Limit::Steps(self.step_limit.unwrap())
+        } else if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            Limit::Time(self.time_limit.unwrap())
+        } else {
+            return Ok(());
+        };
+
+        Err(LimitError { limit })
+    }
+    /// Enters a function call, failing if calls are already nested as deeply as the
+    /// limit allows. Every successful `enter_call` must be matched by `exit_call`.
+    pub fn enter_call(&mut self) -> Result<(), LimitError> {
+        if self.depth >= self.depth_limit {
+            return Err(LimitError {
+                limit: Limit::Depth(self.depth_limit),
+            });
+        }
+        self.depth += 1;
+
+        Ok(())
+    }
+
+    pub fn exit_call(&mut self) {
+        self.depth -= 1;
+    }
+}
@@ -1,31 +1,47 @@
 use std::{
     cell::RefCell,
-    error::Error,
     io::{stdin, stdout, Result, Write},
+    process::ExitCode,
     rc::Rc,
 };
 
-use crate::{interpreter::Interpreter, parser::Parser, resolver::Resolver, scanner::Scanner};
+use crate::{
+    interpreter::{ExitError, Interpreter},
+    parser::Parser,
+    resolver::Resolver,
+    scanner::Scanner,
+};
+
+// Exit codes for scripts that fail, following sysexits.h like the usage error
+const SYNTAX_ERROR_CODE: u8 = 65;
+const RUNTIME_ERROR_CODE: u8 = 70;
 
 #[derive(Default)]
 pub struct Lox {
     interpreter: Rc<RefCell<Interpreter>>,
+    exit_code: Option<u8>,
+    // Set when the last code run had a syntax error or an uncaught runtime error
+    error_code: Option<u8>,
 }
 
 impl Lox {
-    pub fn error(err: Box<dyn Error>) {
-        println!("{}", err);
+    pub fn new(interpreter: Interpreter) -> Self {
+        Self {
+            interpreter: Rc::new(RefCell::new(interpreter)),
+            exit_code: None,
+            error_code: None,
+        }
     }
 
-    pub fn run_file(&mut self, path: &str) -> Result<()> {
+    pub fn run_file(&mut self, path: &str) -> Result<ExitCode> {
         let code = std::fs::read_to_string(path)?;
 
         self.run(&code);
 
-        Ok(())
+        Ok(self.exit_code())
     }
 
-    pub fn run_prompt(&mut self) -> Result<()> {
+    pub fn run_prompt(&mut self) -> Result<ExitCode> {
         let mut input = String::new();
 
         loop {
@@ -37,28 +53,62 @@ impl Lox {
                 break;
             }
 
-            self.run(&input.trim_end());
+            self.run(input.trim_end());
+            if self.exit_code.is_some() {
+                break;
+            }
+            // An error ends the line it was on, not the session
+            self.error_code = None;
         }
 
-        Ok(())
+        Ok(self.exit_code())
     }
 
-    fn run(&mut self, source: &str) {
+    /// The code passed to the script's `exit` call, 65 after a syntax error, 70 after an
+    /// uncaught runtime error or exception, or success otherwise.
+    pub fn exit_code(&self) -> ExitCode {
+        self.exit_code
+            .or(self.error_code)
+            .map_or(ExitCode::SUCCESS, ExitCode::from)
+    }
+
+    pub fn run(&mut self, source: &str) {
         let scanner = Scanner::new(source);
         let mut parser = Parser::new(scanner);
-        // let mut errors: Vec<Box<dyn Error>> = Vec::new();
+        let result = parser.parse();
+        for err in parser.errors() {
+            println!("{}", err);
+            self.error_code = Some(SYNTAX_ERROR_CODE);
+        }
 
-        match parser.parse() {
+        match result {
             Ok(statements) => {
                 let mut resolver = Resolver::new(self.interpreter.clone());
                 resolver.resolve(&statements);
+                for err in resolver.errors() {
+                    println!("{}", err);
+                    self.error_code = Some(SYNTAX_ERROR_CODE);
+                }
+                // Like the book's hadError, code with errors in it is never run
+                if self.error_code.is_some() {
+                    return;
+                }
 
                 match self.interpreter.borrow_mut().interpret(statements) {
                     Ok(_) => {}
-                    Err(err) => println!("{}", err),
+                    Err(err) => match err.downcast_ref::<ExitError>() {
+                        Some(exit) => self.exit_code = Some(exit.code()),
+                        None => {
+                            println!("{}", err);
+                            self.error_code = Some(RUNTIME_ERROR_CODE);
+                        }
+                    },
                 }
             }
-            Err(err) => println!("{}", err),
+            Err(err) => {
+                println!("{}", err);
+                self.error_code = Some(SYNTAX_ERROR_CODE);
+            }
         }
     }
 }
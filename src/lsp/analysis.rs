@@ -0,0 +1,522 @@
+//! What the language server knows about one document: the errors in it and the
+//! names it declares and uses, as found by the scanner, parser and resolver.
+
+use std::{error::Error, rc::Rc};
+
+use crate::{
+    interpreter::RuntimeError,
+    parser::{Parser, ParserError},
+    resolver::{Binding, DeclarationKind, Resolver, Symbols},
+    scanner::{Scanner, ScannerError},
+    token::{Token, TokenKind},
+};
+
+/// A place in the document as editors count it: lines and UTF-16 code units from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Range {
+    fn contains(&self, position: Position) -> bool {
+        self.start <= position && position <= self.end
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub message: String,
+}
+
+/// A function, class, trait or method, with the methods of a class or trait as its
+/// children.
+#[derive(Debug, Clone)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub detail: String,
+    pub kind: DeclarationKind,
+    pub range: Range,
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// The name a symbol refers to. Locals are told apart by their declaration; globals,
+/// methods and properties are looked up by name at runtime, so they are matched by
+/// name here too.
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    Declaration(usize),
+    Global(Rc<str>),
+    Property(Rc<str>),
+}
+
+enum Symbol {
+    Declaration(usize),
+    Reference(usize),
+}
+
+pub struct Analysis {
+    lines: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+    symbols: Symbols,
+}
+
+impl Analysis {
+    pub fn new(source: &str) -> Self {
+        let mut analysis = Self {
+            lines: source.split('\n').map(str::to_string).collect(),
+            diagnostics: Vec::new(),
+            symbols: Symbols::default(),
+        };
+
+        let mut parser = Parser::new(Scanner::new(source));
+        let result = parser.parse();
+        for err in parser.errors() {
+            analysis.report(err.as_ref());
+        }
+
+        match result {
+            Ok(statements) => {
+                // The code never runs, so no interpreter needs to know the scopes
+                let mut resolver = Resolver::without_interpreter();
+                resolver.resolve(&statements);
+                for err in resolver.errors() {
+                    analysis.report(err.as_ref());
+                }
+                analysis.symbols = resolver.symbols().clone();
+            }
+            Err(err) => analysis.report(err.as_ref()),
+        }
+
+        analysis
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Where the name at `position` is declared. Methods and properties can't be
+    /// resolved before the code runs, so every method with that name is a candidate.
+    pub fn definition(&self, position: Position) -> Vec<Range> {
+        let Some(target) = self.target_at(position) else {
+            return Vec::new();
+        };
+
+        self.declarations_of(&target)
+            .map(|declaration| self.name_range(&self.symbols.declarations[declaration].name))
+            .collect()
+    }
+
+    /// Every use of the name at `position`, and its declarations if asked for.
+    pub fn references(&self, position: Position, include_declaration: bool) -> Vec<Range> {
+        let Some(target) = self.target_at(position) else {
+            return Vec::new();
+        };
+
+        let mut ranges: Vec<Range> = Vec::new();
+        if include_declaration {
+            ranges.extend(
+                self.declarations_of(&target).map(|declaration| {
+                    self.name_range(&self.symbols.declarations[declaration].name)
+                }),
+            );
+        }
+        ranges.extend(
+            (0..self.symbols.references.len())
+                .filter(|&reference| self.reference_target(reference) == target)
+                .map(|reference| self.name_range(&self.symbols.references[reference].name)),
+        );
+        ranges.sort_by_key(|range| range.start);
+
+        ranges
+    }
+
+    /// A description of what the name at `position` is, such as `(function) add(a, b)`,
+    /// and the range of the name.
+    pub fn hover(&self, position: Position) -> Option<(String, Range)> {
+        let (symbol, range) = self.symbol_at(position)?;
+        let target = self.symbol_target(&symbol);
+        let descriptions: Vec<String> = self
+            .declarations_of(&target)
+            .map(|declaration| self.describe(declaration))
+            .collect();
+
+        if descriptions.is_empty() {
+            return None;
+        }
+
+        Some((descriptions.join("\n"), range))
+    }
+
+    /// The functions, classes and traits in the document, in source order.
+    pub fn document_symbols(&self) -> Vec<DocumentSymbol> {
+        let declarations = &self.symbols.declarations;
+        let mut symbols: Vec<DocumentSymbol> = Vec::new();
+        // Index into `symbols` of each class or trait, by declaration
+        let mut containers: Vec<(usize, usize)> = Vec::new();
+
+        for (index, declaration) in declarations.iter().enumerate() {
+            let symbol = DocumentSymbol {
+                name: name(&declaration.name).to_string(),
+                detail: self.signature(index),
+                kind: declaration.kind,
+                range: self.name_range(&declaration.name),
+                children: Vec::new(),
+            };
+
+            match declaration.kind {
+                DeclarationKind::Function => symbols.push(symbol),
+                DeclarationKind::Class | DeclarationKind::Trait => {
+                    containers.push((index, symbols.len()));
+                    symbols.push(symbol);
+                }
+                DeclarationKind::Method
+                | DeclarationKind::Getter
+                | DeclarationKind::ClassMethod => {
+                    let container = containers.iter().find(|(declaration, _)| {
+                        Some(*declaration) == declarations[index].container
+                    });
+                    if let Some(&(_, position)) = container {
+                        symbols[position].children.push(symbol);
+                    }
+                }
+                DeclarationKind::Variable | DeclarationKind::Parameter => {}
+            }
+        }
+
+        symbols
+    }
+
+    fn report(&mut self, err: &(dyn Error + 'static)) {
+        let (range, message) = if let Some(err) = err.downcast_ref::<ParserError>() {
+            (self.token_range(err.token()), err.message().to_string())
+        } else if let Some(err) = err.downcast_ref::<RuntimeError>() {
+            (self.token_range(err.token()), err.message().to_string())
+        } else if let Some(err) = err.downcast_ref::<ScannerError>() {
+            (self.line_range(err.line()), err.message().to_string())
+        } else {
+            (self.line_range(1), err.to_string())
+        };
+
+        self.diagnostics.push(Diagnostic { range, message });
+    }
+
+    fn symbol_at(&self, position: Position) -> Option<(Symbol, Range)> {
+        let declarations = self
+            .symbols
+            .declarations
+            .iter()
+            .enumerate()
+            .map(|(index, declaration)| (Symbol::Declaration(index), &declaration.name));
+        let references = self
+            .symbols
+            .references
+            .iter()
+            .enumerate()
+            .map(|(index, reference)| (Symbol::Reference(index), &reference.name));
+
+        declarations
+            .chain(references)
+            .filter(|(_, token)| token.column() > 0)
+            .map(|(symbol, token)| (symbol, self.name_range(token)))
+            .find(|(_, range)| range.contains(position))
+    }
+
+    fn target_at(&self, position: Position) -> Option<Target> {
+        self.symbol_at(position)
+            .map(|(symbol, _)| self.symbol_target(&symbol))
+    }
+
+    fn symbol_target(&self, symbol: &Symbol) -> Target {
+        match symbol {
+            Symbol::Declaration(declaration) => self.declaration_target(*declaration),
+            Symbol::Reference(reference) => self.reference_target(*reference),
+        }
+    }
+
+    fn declaration_target(&self, declaration: usize) -> Target {
+        let declared = &self.symbols.declarations[declaration];
+        match declared.kind {
+            DeclarationKind::Method | DeclarationKind::Getter | DeclarationKind::ClassMethod => {
+                Target::Property(name(&declared.name).into())
+            }
+            _ if declared.global => Target::Global(name(&declared.name).into()),
+            _ => Target::Declaration(declaration),
+        }
+    }
+
+    fn reference_target(&self, reference: usize) -> Target {
+        let used = &self.symbols.references[reference];
+        match used.binding {
+            Binding::Local(declaration) => Target::Declaration(declaration),
+            Binding::Global => Target::Global(name(&used.name).into()),
+            Binding::Property => Target::Property(name(&used.name).into()),
+        }
+    }
+
+    fn declarations_of<'a>(&'a self, target: &'a Target) -> impl Iterator<Item = usize> + 'a {
+        (0..self.symbols.declarations.len())
+            .filter(move |&declaration| self.declaration_target(declaration) == *target)
+    }
+
+    fn describe(&self, declaration: usize) -> String {
+        let declared = &self.symbols.declarations[declaration];
+        let container = declared
+            .container
+            .map(|container| format!("{}.", name(&self.symbols.declarations[container].name)))
+            .unwrap_or_default();
+
+        format!(
+            "({}) {}{}{}",
+            declared.kind,
+            container,
+            name(&declared.name),
+            self.signature(declaration)
+        )
+    }
+
+    /// The parameter list of a function or method, or nothing for other names.
+    fn signature(&self, declaration: usize) -> String {
+        let declared = &self.symbols.declarations[declaration];
+        match declared.kind {
+            DeclarationKind::Function | DeclarationKind::Method | DeclarationKind::ClassMethod => {
+                let params: Vec<&str> = declared.params.iter().map(name).collect();
+                format!("({})", params.join(", "))
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// The range of an identifier token.
+    fn name_range(&self, token: &Token) -> Range {
+        let line = token.line().saturating_sub(1);
+        let start = token.column().saturating_sub(1);
+        let end = start + name(token).chars().count();
+
+        Range {
+            start: self.position(line, start),
+            end: self.position(line, end),
+        }
+    }
+
+    /// The range of the word or symbol a token starts with, since only identifiers
+    /// keep their text.
+    fn token_range(&self, token: &Token) -> Range {
+        let line = token.line().saturating_sub(1);
+        if token.column() == 0 {
+            return self.line_range(*token.line());
+        }
+
+        let start = token.column() - 1;
+        let text: Vec<char> = self.line(line).chars().skip(start).collect();
+        let length = match text.first() {
+            Some(c) if c.is_alphanumeric() || *c == '_' => text
+                .iter()
+                .take_while(|c| c.is_alphanumeric() || **c == '_')
+                .count(),
+            Some(_) => 1,
+            None => 0,
+        };
+
+        Range {
+            start: self.position(line, start),
+            end: self.position(line, start + length),
+        }
+    }
+
+    /// The range of a whole line, counting lines from 1 as the scanner does.
+    fn line_range(&self, line: usize) -> Range {
+        let line = line.saturating_sub(1);
+
+        Range {
+            start: self.position(line, 0),
+            end: self.position(line, self.line(line).chars().count()),
+        }
+    }
+
+    fn line(&self, line: usize) -> &str {
+        self.lines.get(line).map_or("", String::as_str)
+    }
+
+    /// Converts a column in characters into one in UTF-16 code units.
+    fn position(&self, line: usize, column: usize) -> Position {
+        let character = self
+            .line(line)
+            .chars()
+            .take(column)
+            .map(char::len_utf16)
+            .sum();
+
+        Position { line, character }
+    }
+}
+
+fn name(token: &Token) -> &str {
+    match token.kind() {
+        TokenKind::Identifier(id) => id,
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gc;
+
+    const SOURCE: &str = "var total = 0;
+fun add(a, b) {
+  return a + b;
+}
+class Counter {
+  init() { this.count = 0; }
+  bump() { this.count = add(this.count, 1); }
+}
+total = add(total, 2);
+";
+
+    fn at(line: usize, character: usize) -> Position {
+        Position { line, character }
+    }
+
+    fn range(line: usize, start: usize, end: usize) -> Range {
+        Range {
+            start: at(line, start),
+            end: at(line, end),
+        }
+    }
+
+    #[test]
+    fn analysing_leaves_the_gc_heap_alone() {
+        let tracked = gc::tracked();
+        for _ in 0..10 {
+            Analysis::new(SOURCE);
+        }
+
+        assert_eq!(gc::tracked(), tracked);
+    }
+
+    #[test]
+    fn valid_code_has_no_diagnostics() {
+        assert!(Analysis::new(SOURCE).diagnostics().is_empty());
+    }
+
+    #[test]
+    fn parse_errors_are_reported_at_their_token() {
+        let analysis = Analysis::new("var x = 1;\nvar = 2;\n");
+        let diagnostics = analysis.diagnostics();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range, range(1, 4, 5));
+        assert_eq!(diagnostics[0].message, "Expected variable name");
+    }
+
+    #[test]
+    fn resolver_errors_are_reported() {
+        let analysis = Analysis::new("return 1;\n");
+        let diagnostics = analysis.diagnostics();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range, range(0, 0, 6));
+        assert_eq!(diagnostics[0].message, "Can't return from top-level code.");
+    }
+
+    #[test]
+    fn definition_of_a_local_is_its_declaration() {
+        let analysis = Analysis::new(SOURCE);
+
+        assert_eq!(analysis.definition(at(2, 9)), vec![range(1, 8, 9)]);
+        assert_eq!(analysis.definition(at(2, 13)), vec![range(1, 11, 12)]);
+    }
+
+    #[test]
+    fn definition_of_a_global_is_found_from_any_use() {
+        let analysis = Analysis::new(SOURCE);
+
+        assert_eq!(analysis.definition(at(8, 9)), vec![range(1, 4, 7)]);
+        assert_eq!(analysis.definition(at(6, 25)), vec![range(1, 4, 7)]);
+    }
+
+    #[test]
+    fn definition_away_from_a_name_is_empty() {
+        assert!(Analysis::new(SOURCE).definition(at(3, 0)).is_empty());
+    }
+
+    #[test]
+    fn references_are_sorted_and_may_include_the_declaration() {
+        let analysis = Analysis::new(SOURCE);
+
+        assert_eq!(
+            analysis.references(at(0, 4), true),
+            vec![range(0, 4, 9), range(8, 0, 5), range(8, 12, 17)]
+        );
+        assert_eq!(
+            analysis.references(at(8, 0), false),
+            vec![range(8, 0, 5), range(8, 12, 17)]
+        );
+    }
+
+    #[test]
+    fn properties_are_matched_by_name() {
+        let analysis = Analysis::new(SOURCE);
+
+        assert_eq!(
+            analysis.references(at(5, 16), true),
+            vec![range(5, 16, 21), range(6, 16, 21), range(6, 33, 38)]
+        );
+    }
+
+    #[test]
+    fn hover_describes_the_declaration() {
+        let analysis = Analysis::new(SOURCE);
+
+        assert_eq!(
+            analysis.hover(at(8, 8)),
+            Some(("(function) add(a, b)".to_string(), range(8, 8, 11)))
+        );
+        assert_eq!(
+            analysis.hover(at(6, 3)),
+            Some(("(method) Counter.bump()".to_string(), range(6, 2, 6)))
+        );
+        assert_eq!(analysis.hover(at(3, 0)), None);
+    }
+
+    #[test]
+    fn document_symbols_nest_methods_in_their_class() {
+        let symbols = Analysis::new(SOURCE).document_symbols();
+        let names: Vec<(&str, DeclarationKind)> = symbols
+            .iter()
+            .map(|symbol| (symbol.name.as_str(), symbol.kind))
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                ("add", DeclarationKind::Function),
+                ("Counter", DeclarationKind::Class)
+            ]
+        );
+        assert_eq!(symbols[0].detail, "(a, b)");
+
+        let methods: Vec<&str> = symbols[1]
+            .children
+            .iter()
+            .map(|method| method.name.as_str())
+            .collect();
+        assert_eq!(methods, vec!["init", "bump"]);
+    }
+
+    #[test]
+    fn positions_count_utf16_code_units() {
+        let analysis = Analysis::new("var s = \"x\";\nprint \"😀\" + s;\n");
+
+        assert_eq!(analysis.definition(at(1, 13)), vec![range(0, 4, 5)]);
+        assert_eq!(analysis.references(at(0, 4), false), vec![range(1, 13, 14)]);
+    }
+}
@@ -0,0 +1,682 @@
+//! A language server for Lox, speaking the Language Server Protocol over stdin and
+//! stdout. Editors start it with `rlox lsp`. It keeps the text of every open document
+//! and re-analyses a document whenever it changes, publishing the parser and resolver
+//! errors as diagnostics.
+
+mod analysis;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, stdin, stdout, BufRead, ErrorKind, Write},
+    process::ExitCode,
+};
+
+use crate::{json::Json, resolver::DeclarationKind};
+
+use analysis::{Analysis, DocumentSymbol, Position, Range};
+
+// JSON-RPC error codes
+const PARSE_ERROR: f64 = -32700.0;
+const INVALID_REQUEST: f64 = -32600.0;
+const METHOD_NOT_FOUND: f64 = -32601.0;
+
+// LSP symbol kinds
+const SYMBOL_CLASS: f64 = 5.0;
+const SYMBOL_METHOD: f64 = 6.0;
+const SYMBOL_PROPERTY: f64 = 7.0;
+const SYMBOL_INTERFACE: f64 = 11.0;
+const SYMBOL_FUNCTION: f64 = 12.0;
+const SYMBOL_VARIABLE: f64 = 13.0;
+
+/// Serves one editor session until it sends `exit` or closes stdin.
+pub fn run() -> io::Result<ExitCode> {
+    let mut server = Server::default();
+    let mut input = stdin().lock();
+    let mut output = stdout().lock();
+
+    while let Some(body) = read_message(&mut input)? {
+        let replies = match Json::parse(&body) {
+            Ok(message) => server.handle(&message),
+            Err(err) => vec![error_response(Json::Null, PARSE_ERROR, err.to_string())],
+        };
+        for reply in replies {
+            write_message(&mut output, &reply)?;
+        }
+
+        if let Some(code) = server.exit_code {
+            return Ok(code);
+        }
+    }
+
+    // The editor went away without asking the server to exit
+    Ok(ExitCode::FAILURE)
+}
+
+/// Reads the body of the next message, or `None` at the end of the input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "Message is missing its Content-Length header",
+        ));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+}
+
+fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.stringify(None);
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<String, Analysis>,
+    shutting_down: bool,
+    exit_code: Option<ExitCode>,
+}
+
+impl Server {
+    /// Handles a request or notification and returns the messages to send back.
+    fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message.get("method").and_then(Json::as_str);
+        let params = message.get("params").unwrap_or(&Json::Null);
+
+        match (method, message.get("id")) {
+            (Some(method), Some(id)) => vec![self.request(method, params, id.clone())],
+            (Some(method), None) => self.notification(method, params),
+            // The server sends no requests, so there are no responses to handle
+            (None, _) => Vec::new(),
+        }
+    }
+
+    fn request(&mut self, method: &str, params: &Json, id: Json) -> Json {
+        if self.shutting_down {
+            return error_response(
+                id,
+                INVALID_REQUEST,
+                "The server is shutting down".to_string(),
+            );
+        }
+
+        let result = match method {
+            "initialize" => capabilities(),
+            "shutdown" => {
+                self.shutting_down = true;
+                Json::Null
+            }
+            "textDocument/definition" => match self.document_position(params) {
+                Some((analysis, position)) => Json::Array(
+                    analysis
+                        .definition(position)
+                        .into_iter()
+                        .map(|range| location(params, range))
+                        .collect(),
+                ),
+                None => Json::Null,
+            },
+            "textDocument/references" => match self.document_position(params) {
+                Some((analysis, position)) => {
+                    let include_declaration = params
+                        .get("context")
+                        .and_then(|context| context.get("includeDeclaration"))
+                        .and_then(Json::as_bool)
+                        .unwrap_or(true);
+
+                    Json::Array(
+                        analysis
+                            .references(position, include_declaration)
+                            .into_iter()
+                            .map(|range| location(params, range))
+                            .collect(),
+                    )
+                }
+                None => Json::Null,
+            },
+            "textDocument/hover" => match self
+                .document_position(params)
+                .and_then(|(analysis, position)| analysis.hover(position))
+            {
+                Some((description, range)) => object([
+                    (
+                        "contents",
+                        object([
+                            ("kind", Json::String("markdown".to_string())),
+                            (
+                                "value",
+                                Json::String(format!("```lox\n{}\n```", description)),
+                            ),
+                        ]),
+                    ),
+                    ("range", range_json(range)),
+                ]),
+                None => Json::Null,
+            },
+            "textDocument/documentSymbol" => match self.document(params) {
+                Some(analysis) => Json::Array(
+                    analysis
+                        .document_symbols()
+                        .iter()
+                        .map(document_symbol)
+                        .collect(),
+                ),
+                None => Json::Null,
+            },
+            _ => {
+                return error_response(id, METHOD_NOT_FOUND, format!("Unknown method '{}'", method))
+            }
+        };
+
+        object([
+            ("jsonrpc", Json::String("2.0".to_string())),
+            ("id", id),
+            ("result", result),
+        ])
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        if method == "exit" {
+            self.exit_code = Some(if self.shutting_down {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            });
+            return Vec::new();
+        }
+
+        let Some(uri) = document_uri(params) else {
+            return Vec::new();
+        };
+
+        let text = match method {
+            "textDocument/didOpen" => params
+                .get("textDocument")
+                .and_then(|document| document.get("text"))
+                .and_then(Json::as_str),
+            // Only full-document sync is offered, so the last change is the whole text
+            "textDocument/didChange" => match params.get("contentChanges") {
+                Some(Json::Array(changes)) => changes
+                    .last()
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str),
+                _ => None,
+            },
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![publish_diagnostics(uri, Vec::new())];
+            }
+            _ => None,
+        };
+        let Some(text) = text else {
+            return Vec::new();
+        };
+
+        let analysis = Analysis::new(text);
+        let diagnostics = analysis
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| {
+                object([
+                    ("range", range_json(diagnostic.range)),
+                    ("severity", Json::Number(1.0)),
+                    ("source", Json::String("rlox".to_string())),
+                    ("message", Json::String(diagnostic.message.clone())),
+                ])
+            })
+            .collect();
+        self.documents.insert(uri.to_string(), analysis);
+
+        vec![publish_diagnostics(uri, diagnostics)]
+    }
+
+    fn document(&self, params: &Json) -> Option<&Analysis> {
+        self.documents.get(document_uri(params)?)
+    }
+
+    fn document_position(&self, params: &Json) -> Option<(&Analysis, Position)> {
+        let position = params.get("position")?;
+        let position = Position {
+            line: position.get("line")?.as_f64()? as usize,
+            character: position.get("character")?.as_f64()? as usize,
+        };
+
+        Some((self.document(params)?, position))
+    }
+}
+
+fn capabilities() -> Json {
+    object([
+        (
+            "capabilities",
+            object([
+                // Full-document sync
+                ("textDocumentSync", Json::Number(1.0)),
+                ("definitionProvider", Json::Boolean(true)),
+                ("referencesProvider", Json::Boolean(true)),
+                ("hoverProvider", Json::Boolean(true)),
+                ("documentSymbolProvider", Json::Boolean(true)),
+            ]),
+        ),
+        (
+            "serverInfo",
+            object([
+                ("name", Json::String("rlox".to_string())),
+                (
+                    "version",
+                    Json::String(env!("CARGO_PKG_VERSION").to_string()),
+                ),
+            ]),
+        ),
+    ])
+}
+
+fn document_uri(params: &Json) -> Option<&str> {
+    params.get("textDocument")?.get("uri")?.as_str()
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    object([
+        ("jsonrpc", Json::String("2.0".to_string())),
+        (
+            "method",
+            Json::String("textDocument/publishDiagnostics".to_string()),
+        ),
+        (
+            "params",
+            object([
+                ("uri", Json::String(uri.to_string())),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        ),
+    ])
+}
+
+fn error_response(id: Json, code: f64, message: String) -> Json {
+    object([
+        ("jsonrpc", Json::String("2.0".to_string())),
+        ("id", id),
+        (
+            "error",
+            object([
+                ("code", Json::Number(code)),
+                ("message", Json::String(message)),
+            ]),
+        ),
+    ])
+}
+
+/// A location in the document named by the request's parameters.
+fn location(params: &Json, range: Range) -> Json {
+    object([
+        (
+            "uri",
+            Json::String(document_uri(params).unwrap_or_default().to_string()),
+        ),
+        ("range", range_json(range)),
+    ])
+}
+
+fn document_symbol(symbol: &DocumentSymbol) -> Json {
+    let kind = match symbol.kind {
+        DeclarationKind::Class => SYMBOL_CLASS,
+        DeclarationKind::Trait => SYMBOL_INTERFACE,
+        DeclarationKind::Function => SYMBOL_FUNCTION,
+        DeclarationKind::Method | DeclarationKind::ClassMethod => SYMBOL_METHOD,
+        DeclarationKind::Getter => SYMBOL_PROPERTY,
+        DeclarationKind::Variable | DeclarationKind::Parameter => SYMBOL_VARIABLE,
+    };
+
+    object([
+        ("name", Json::String(symbol.name.clone())),
+        ("detail", Json::String(symbol.detail.clone())),
+        ("kind", Json::Number(kind)),
+        ("range", range_json(symbol.range)),
+        ("selectionRange", range_json(symbol.range)),
+        (
+            "children",
+            Json::Array(symbol.children.iter().map(document_symbol).collect()),
+        ),
+    ])
+}
+
+fn range_json(range: Range) -> Json {
+    object([
+        ("start", position_json(range.start)),
+        ("end", position_json(range.end)),
+    ])
+}
+
+fn position_json(position: Position) -> Json {
+    object([
+        ("line", Json::Number(position.line as f64)),
+        ("character", Json::Number(position.character as f64)),
+    ])
+}
+
+fn object<const N: usize>(members: [(&str, Json); N]) -> Json {
+    Json::Object(
+        members
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect::<BTreeMap<String, Json>>(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const URI: &str = "file:///test.lox";
+
+    fn framed(body: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    fn string(text: &str) -> Json {
+        Json::String(text.to_string())
+    }
+
+    fn notification(server: &mut Server, method: &str, params: Json) -> Vec<Json> {
+        server.handle(&object([
+            ("jsonrpc", string("2.0")),
+            ("method", string(method)),
+            ("params", params),
+        ]))
+    }
+
+    fn request(server: &mut Server, method: &str, params: Json) -> Json {
+        let mut replies = server.handle(&object([
+            ("jsonrpc", string("2.0")),
+            ("id", Json::Number(1.0)),
+            ("method", string(method)),
+            ("params", params),
+        ]));
+
+        assert_eq!(replies.len(), 1);
+        replies.remove(0)
+    }
+
+    fn open(server: &mut Server, text: &str) -> Vec<Json> {
+        notification(
+            server,
+            "textDocument/didOpen",
+            object([(
+                "textDocument",
+                object([("uri", string(URI)), ("text", string(text))]),
+            )]),
+        )
+    }
+
+    fn at(line: usize, character: usize) -> Json {
+        object([
+            ("textDocument", object([("uri", string(URI))])),
+            ("position", position_json(Position { line, character })),
+        ])
+    }
+
+    fn range(line: usize, start: usize, end: usize) -> Json {
+        range_json(Range {
+            start: Position {
+                line,
+                character: start,
+            },
+            end: Position {
+                line,
+                character: end,
+            },
+        })
+    }
+
+    fn diagnostics(replies: &[Json]) -> &[Json] {
+        assert_eq!(replies.len(), 1);
+        assert_eq!(
+            replies[0].get("method").and_then(Json::as_str),
+            Some("textDocument/publishDiagnostics")
+        );
+        match replies[0]
+            .get("params")
+            .and_then(|params| params.get("diagnostics"))
+        {
+            Some(Json::Array(diagnostics)) => diagnostics,
+            other => panic!("Expected a diagnostics array but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn read_message_reads_one_body_at_a_time() {
+        let mut input = Cursor::new(framed("{\"a\":1}") + &framed("[]"));
+
+        assert_eq!(
+            read_message(&mut input).unwrap().as_deref(),
+            Some("{\"a\":1}")
+        );
+        assert_eq!(read_message(&mut input).unwrap().as_deref(), Some("[]"));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+    }
+
+    #[test]
+    fn read_message_counts_the_length_in_bytes_and_skips_other_headers() {
+        let body = "\"é\"";
+        let mut input = Cursor::new(format!(
+            "Content-Type: application/vscode-jsonrpc; charset=utf-8\r\n{}",
+            framed(body)
+        ));
+
+        assert_eq!(read_message(&mut input).unwrap().as_deref(), Some(body));
+    }
+
+    #[test]
+    fn read_message_needs_a_content_length() {
+        let mut input = Cursor::new("Content-Type: text/plain\r\n\r\n{}");
+        let err = read_message(&mut input).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn written_messages_read_back() {
+        let message = object([("id", Json::Number(7.0)), ("result", Json::Null)]);
+        let mut output = Vec::new();
+        write_message(&mut output, &message).unwrap();
+
+        let body = read_message(&mut Cursor::new(output)).unwrap().unwrap();
+        assert_eq!(Json::parse(&body).unwrap(), message);
+    }
+
+    #[test]
+    fn initialize_lists_the_capabilities() {
+        let reply = request(&mut Server::default(), "initialize", Json::Null);
+        let capabilities = reply
+            .get("result")
+            .and_then(|result| result.get("capabilities"))
+            .unwrap();
+
+        assert_eq!(reply.get("id"), Some(&Json::Number(1.0)));
+        for provider in [
+            "definitionProvider",
+            "referencesProvider",
+            "hoverProvider",
+            "documentSymbolProvider",
+        ] {
+            assert_eq!(capabilities.get(provider), Some(&Json::Boolean(true)));
+        }
+    }
+
+    #[test]
+    fn opening_changing_and_closing_publish_diagnostics() {
+        let mut server = Server::default();
+
+        let replies = open(&mut server, "var x = 1;\nvar = 2;\n");
+        let published = diagnostics(&replies);
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].get("range"), Some(&range(1, 4, 5)));
+        assert_eq!(
+            published[0].get("message").and_then(Json::as_str),
+            Some("Expected variable name")
+        );
+
+        let replies = notification(
+            &mut server,
+            "textDocument/didChange",
+            object([
+                ("textDocument", object([("uri", string(URI))])),
+                (
+                    "contentChanges",
+                    Json::Array(vec![object([("text", string("var x = 1;\n"))])]),
+                ),
+            ]),
+        );
+        assert!(diagnostics(&replies).is_empty());
+
+        let replies = notification(
+            &mut server,
+            "textDocument/didClose",
+            object([("textDocument", object([("uri", string(URI))]))]),
+        );
+        assert!(diagnostics(&replies).is_empty());
+        assert_eq!(
+            request(&mut server, "textDocument/definition", at(0, 4)).get("result"),
+            Some(&Json::Null)
+        );
+    }
+
+    #[test]
+    fn definition_and_references_return_locations() {
+        let mut server = Server::default();
+        open(&mut server, "var x = 1;\nprint x;\nprint x + 1;\n");
+
+        let location =
+            |line, start, end| object([("uri", string(URI)), ("range", range(line, start, end))]);
+
+        let reply = request(&mut server, "textDocument/definition", at(2, 6));
+        assert_eq!(
+            reply.get("result"),
+            Some(&Json::Array(vec![location(0, 4, 5)]))
+        );
+
+        let mut params = at(0, 4);
+        if let Json::Object(members) = &mut params {
+            members.insert(
+                "context".to_string(),
+                object([("includeDeclaration", Json::Boolean(false))]),
+            );
+        }
+        let reply = request(&mut server, "textDocument/references", params);
+        assert_eq!(
+            reply.get("result"),
+            Some(&Json::Array(vec![location(1, 6, 7), location(2, 6, 7)]))
+        );
+    }
+
+    #[test]
+    fn hover_shows_the_declaration_as_markdown() {
+        let mut server = Server::default();
+        open(
+            &mut server,
+            "fun add(a, b) {\n  return a + b;\n}\nadd(1, 2);\n",
+        );
+
+        let reply = request(&mut server, "textDocument/hover", at(3, 1));
+        let result = reply.get("result").unwrap();
+        assert_eq!(
+            result
+                .get("contents")
+                .and_then(|contents| contents.get("value"))
+                .and_then(Json::as_str),
+            Some("```lox\n(function) add(a, b)\n```")
+        );
+        assert_eq!(result.get("range"), Some(&range(3, 0, 3)));
+
+        let reply = request(&mut server, "textDocument/hover", at(2, 0));
+        assert_eq!(reply.get("result"), Some(&Json::Null));
+    }
+
+    #[test]
+    fn document_symbols_have_kinds_and_children() {
+        let mut server = Server::default();
+        open(&mut server, "class A {\n  go() {}\n}\nfun f() {}\n");
+
+        let reply = request(
+            &mut server,
+            "textDocument/documentSymbol",
+            object([("textDocument", object([("uri", string(URI))]))]),
+        );
+        let Some(Json::Array(symbols)) = reply.get("result") else {
+            panic!("Expected a symbol array but got {:?}", reply);
+        };
+
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[0].get("name").and_then(Json::as_str), Some("A"));
+        assert_eq!(symbols[0].get("kind"), Some(&Json::Number(SYMBOL_CLASS)));
+        assert_eq!(symbols[1].get("name").and_then(Json::as_str), Some("f"));
+        assert_eq!(symbols[1].get("kind"), Some(&Json::Number(SYMBOL_FUNCTION)));
+
+        let Some(Json::Array(children)) = symbols[0].get("children") else {
+            panic!("Expected a children array but got {:?}", symbols[0]);
+        };
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].get("name").and_then(Json::as_str), Some("go"));
+        assert_eq!(children[0].get("kind"), Some(&Json::Number(SYMBOL_METHOD)));
+    }
+
+    #[test]
+    fn unknown_requests_are_errors_and_unknown_notifications_are_ignored() {
+        let mut server = Server::default();
+
+        let reply = request(&mut server, "textDocument/rename", Json::Null);
+        assert_eq!(
+            reply.get("error").and_then(|error| error.get("code")),
+            Some(&Json::Number(METHOD_NOT_FOUND))
+        );
+        assert!(notification(&mut server, "$/cancelRequest", Json::Null).is_empty());
+    }
+
+    #[test]
+    fn exit_after_shutdown_succeeds() {
+        let mut server = Server::default();
+
+        let reply = request(&mut server, "shutdown", Json::Null);
+        assert_eq!(reply.get("result"), Some(&Json::Null));
+        assert_eq!(server.exit_code, None);
+
+        let reply = request(&mut server, "initialize", Json::Null);
+        assert_eq!(
+            reply.get("error").and_then(|error| error.get("code")),
+            Some(&Json::Number(INVALID_REQUEST))
+        );
+
+        assert!(notification(&mut server, "exit", Json::Null).is_empty());
+        assert_eq!(server.exit_code, Some(ExitCode::SUCCESS));
+    }
+
+    #[test]
+    fn exit_without_shutdown_fails() {
+        let mut server = Server::default();
+        notification(&mut server, "exit", Json::Null);
+
+        assert_eq!(server.exit_code, Some(ExitCode::FAILURE));
+    }
+}
@@ -1,43 +1,142 @@
-use std::process::ExitCode;
+use std::{panic, process::ExitCode, thread, time::Duration};
 
-// use ast_printer::AstPrinter;
-use lox::Lox;
+// use rlox::ast_printer::AstPrinter;
+use rlox::{
+    interpreter::{Interpreter, InterpreterBuilder},
+    lox::Lox,
+    stdlib::Capability,
+};
 
-mod ast;
-// mod ast_printer;
-mod environment;
-mod interpreter;
-mod lox;
-mod parser;
-mod resolver;
-mod scanner;
-mod token;
+const USAGE: &str = "Usage: rlox [--allow groups] [--step-limit steps] \
+                     [--time-limit seconds] [--memory-limit bytes] [--depth-limit calls] \
+                     [--deterministic seed] [script [args...]]\n       rlox lsp";
+
+/// The stack the interpreter runs on. Lox calls recurse on the Rust stack, and the
+/// main thread's is too small for the default call depth limit in debug builds.
+const STACK_SIZE: usize = 64 * 1024 * 1024;
 
 fn main() -> ExitCode {
+    match thread::Builder::new().stack_size(STACK_SIZE).spawn(run) {
+        Ok(interpreter) => interpreter
+            .join()
+            .unwrap_or_else(|err| panic::resume_unwind(err)),
+        Err(err) => {
+            println!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> ExitCode {
     let args = std::env::args().collect::<Vec<String>>();
+    // `rlox lsp` starts the language server, and `--lsp` is accepted for it too. It
+    // takes no options or arguments, so a script named `lsp` is run as `./lsp`.
+    if args
+        .get(1)
+        .is_some_and(|arg| arg == "lsp" || arg == "--lsp")
+    {
+        return match &args[2..] {
+            [] => rlox::lsp::run().unwrap_or_else(|err| {
+                println!("{}", err);
+                ExitCode::FAILURE
+            }),
+            _ => usage(),
+        };
+    }
 
-    if args.len() > 2 {
-        println!("Usage: rlox [script]");
-        return ExitCode::FAILURE;
-    } else if args.len() == 2 {
-        let mut lox = Lox::default();
+    let mut capabilities = None;
+    let mut step_limit = None;
+    let mut time_limit = None;
+    let mut memory_limit = None;
+    let mut depth_limit = None;
+    let mut seed = None;
 
-        match lox.run_file(&args[1]) {
-            Ok(_) => return ExitCode::SUCCESS,
-            Err(err) => {
-                println!("{}", err);
-                return ExitCode::FAILURE;
-            }
+    // Options come before the script, since everything after it is passed to the script
+    let mut rest = &args[1..];
+    while let [option, value, remaining @ ..] = rest {
+        match option.as_str() {
+            "--allow" => match parse_capabilities(value) {
+                Some(allowed) => capabilities = Some(allowed),
+                None => return usage(),
+            },
+            "--step-limit" => match value.parse::<u64>() {
+                Ok(steps) => step_limit = Some(steps),
+                Err(_) => return usage(),
+            },
+            "--time-limit" => match value.parse::<f64>().map(Duration::try_from_secs_f64) {
+                Ok(Ok(time)) => time_limit = Some(time),
+                _ => return usage(),
+            },
+            "--memory-limit" => match value.parse::<usize>() {
+                Ok(bytes) => memory_limit = Some(bytes),
+                Err(_) => return usage(),
+            },
+            "--depth-limit" => match value.parse::<usize>() {
+                Ok(depth) => depth_limit = Some(depth),
+                Err(_) => return usage(),
+            },
+            "--deterministic" => match value.parse::<u64>() {
+                Ok(value) => seed = Some(value),
+                Err(_) => return usage(),
+            },
+            _ => break,
+        }
+        rest = remaining;
+    }
+    // Unknown options, and options given to the language server, are mistakes rather
+    // than a script to run
+    if rest
+        .first()
+        .is_some_and(|arg| arg.starts_with("--") || arg == "lsp")
+    {
+        return usage();
+    }
+
+    let mut interpreter = match capabilities {
+        Some(allowed) => allowed
+            .into_iter()
+            .fold(InterpreterBuilder::new(), InterpreterBuilder::allow)
+            .build(),
+        None => Interpreter::default(),
+    };
+    interpreter.set_step_limit(step_limit);
+    interpreter.set_time_limit(time_limit);
+    interpreter.set_memory_limit(memory_limit);
+    if let Some(depth) = depth_limit {
+        interpreter.set_depth_limit(depth);
+    }
+    if let Some(seed) = seed {
+        interpreter.set_deterministic(seed);
+    }
+
+    let result = match rest {
+        [path, script_args @ ..] => {
+            interpreter.set_args(script_args.to_vec());
+            Lox::new(interpreter).run_file(path)
         }
-    } else {
-        let mut lox = Lox::default();
+        [] => Lox::new(interpreter).run_prompt(),
+    };
 
-        match lox.run_prompt() {
-            Ok(_) => return ExitCode::SUCCESS,
-            Err(err) => {
-                println!("{}", err);
-                return ExitCode::FAILURE;
-            }
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            println!("{}", err);
+            ExitCode::FAILURE
         }
     }
 }
+
+/// Parses a comma-separated list of native groups, such as `math,string`. An empty
+/// list allows no natives at all.
+fn parse_capabilities(names: &str) -> Option<Vec<Capability>> {
+    names
+        .split(',')
+        .filter(|name| !name.is_empty())
+        .map(Capability::from_name)
+        .collect()
+}
+
+fn usage() -> ExitCode {
+    println!("{}", USAGE);
+    ExitCode::from(64)
+}
@@ -2,12 +2,12 @@ use std::{error::Error, fmt::Display};
 
 use crate::{
     ast::{
-        Assign, Binary, Block, Call, Class, Expr, Expression, Function, Get, Grouping, IfStmt,
-        Literal, Logical, Print, ReturnStmt, Set, Stmt, SuperExpr, This, Unary, Var, Variable,
+        Assign, Binary, Block, Call, Class, CompoundSet, Conditional, Expr, Expression, Function,
+        Get, Grouping, IfStmt, Index, Literal, Logical, Postfix, Print, ReturnStmt, Set, SetIndex,
+        Stmt, Stringify, SuperExpr, This, ThrowStmt, TraitStmt, TryStmt, Unary, Var, Variable,
         WhileStmt,
     },
     interpreter::RuntimeError,
-    lox::Lox,
     scanner::Scanner,
     token::{Token, TokenKind},
 };
@@ -24,6 +24,14 @@ impl ParserError {
     pub fn new(token: Token, message: String) -> Self {
         Self { token, message }
     }
+
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
 }
 
 impl Error for ParserError {}
@@ -58,16 +66,25 @@ impl Display for FunctionKind {
 pub struct Parser {
     scanner: Scanner,
     current_token: Token,
+    // Errors the parser recovered from, in the order they were found
+    errors: Vec<Box<dyn Error>>,
 }
 
 impl Parser {
     pub fn new(scanner: Scanner) -> Self {
         Self {
-            scanner: scanner,
+            scanner,
             current_token: Token::new(TokenKind::Eof, 0),
+            errors: Vec::new(),
         }
     }
 
+    /// The errors reported while parsing. Statements with errors are left out of the
+    /// parsed program, and parsing carries on with the next statement.
+    pub fn errors(&self) -> &[Box<dyn Error>] {
+        &self.errors
+    }
+
     pub fn parse(&mut self) -> ParserResult<Vec<Stmt>> {
         self.current_token = self.scanner.get_next_token()?;
 
@@ -76,7 +93,7 @@ impl Parser {
         while self.current_token.kind() != &TokenKind::Eof {
             match self.declaration() {
                 Ok(statement) => statements.push(statement),
-                Err(err) => Lox::error(err),
+                Err(err) => self.errors.push(err),
             }
         }
 
@@ -99,7 +116,10 @@ impl Parser {
                 | TokenKind::If
                 | TokenKind::While
                 | TokenKind::Print
-                | TokenKind::Return => return Ok(()),
+                | TokenKind::Return
+                | TokenKind::Throw
+                | TokenKind::Trait
+                | TokenKind::Try => return Ok(()),
                 _ => self.current_token = self.scanner.get_next_token()?,
             }
         }
@@ -142,6 +162,17 @@ impl Parser {
                     }
                 }
             }
+            TokenKind::Trait => {
+                self.current_token = self.scanner.get_next_token()?;
+
+                match self.trait_declaration() {
+                    Ok(statement) => return Ok(statement),
+                    Err(err) => {
+                        self.synchronize()?;
+                        return Err(err);
+                    }
+                }
+            }
             _ => {}
         }
 
@@ -201,53 +232,58 @@ impl Parser {
         };
         self.current_token = self.scanner.get_next_token()?;
 
-        if !matches!(self.current_token.kind(), TokenKind::LeftParen) {
-            return Err(Box::new(ParserError::new(
-                self.current_token.clone(),
-                format!("Expect '(' after {} name", kind),
-            )));
-        }
-        self.current_token = self.scanner.get_next_token()?;
+        // A method with no parameter list is a getter, run when the property is read
+        let is_getter = matches!(kind, FunctionKind::Method)
+            && matches!(self.current_token.kind(), TokenKind::LeftBrace);
 
         let mut parameters: Vec<Token> = Vec::new();
+        if !is_getter {
+            if !matches!(self.current_token.kind(), TokenKind::LeftParen) {
+                return Err(Box::new(ParserError::new(
+                    self.current_token.clone(),
+                    format!("Expect '(' after {} name", kind),
+                )));
+            }
+            self.current_token = self.scanner.get_next_token()?;
 
-        if !matches!(self.current_token.kind(), TokenKind::RightParen) {
-            loop {
-                if parameters.len() >= 255 {
-                    Lox::error(Box::new(ParserError::new(
-                        self.current_token.clone(),
-                        "Can't have more than 255 parameters".to_string(),
-                    )));
-                }
-
-                match self.current_token.kind() {
-                    TokenKind::Identifier(_) => {
-                        let temp = self.current_token.clone();
-                        self.current_token = self.scanner.get_next_token()?;
-                        parameters.push(temp);
-                    }
-                    _ => {
-                        return Err(Box::new(ParserError::new(
+            if !matches!(self.current_token.kind(), TokenKind::RightParen) {
+                loop {
+                    if parameters.len() >= 255 {
+                        self.errors.push(Box::new(ParserError::new(
                             self.current_token.clone(),
-                            "Expect parameter name".to_string(),
-                        )))
+                            "Can't have more than 255 parameters".to_string(),
+                        )));
                     }
-                }
 
-                if !matches!(self.current_token.kind(), TokenKind::Comma) {
-                    break;
+                    match self.current_token.kind() {
+                        TokenKind::Identifier(_) => {
+                            let temp = self.current_token.clone();
+                            self.current_token = self.scanner.get_next_token()?;
+                            parameters.push(temp);
+                        }
+                        _ => {
+                            return Err(Box::new(ParserError::new(
+                                self.current_token.clone(),
+                                "Expect parameter name".to_string(),
+                            )))
+                        }
+                    }
+
+                    if !matches!(self.current_token.kind(), TokenKind::Comma) {
+                        break;
+                    }
+                    self.current_token = self.scanner.get_next_token()?;
                 }
-                self.current_token = self.scanner.get_next_token()?;
             }
-        }
 
-        if !matches!(self.current_token.kind(), TokenKind::RightParen) {
-            return Err(Box::new(ParserError::new(
-                self.current_token.clone(),
-                "Expect ')' after parameters".to_string(),
-            )));
+            if !matches!(self.current_token.kind(), TokenKind::RightParen) {
+                return Err(Box::new(ParserError::new(
+                    self.current_token.clone(),
+                    "Expect ')' after parameters".to_string(),
+                )));
+            }
+            self.current_token = self.scanner.get_next_token()?;
         }
-        self.current_token = self.scanner.get_next_token()?;
 
         if !matches!(self.current_token.kind(), TokenKind::LeftBrace) {
             return Err(Box::new(ParserError::new(
@@ -259,7 +295,9 @@ impl Parser {
 
         let body = self.block()?;
 
-        Ok(Stmt::Function(Function::new(name, parameters, body)))
+        Ok(Stmt::Function(Function::new(
+            name, parameters, body, is_getter,
+        )))
     }
 
     fn class_declaration(&mut self) -> ParserResult<Stmt> {
@@ -292,35 +330,101 @@ impl Parser {
             _ => None,
         };
 
+        let mut traits: Vec<Expr> = Vec::new();
+        if matches!(self.current_token.kind(), TokenKind::With) {
+            loop {
+                self.current_token = self.scanner.get_next_token()?;
+
+                if !matches!(self.current_token.kind(), TokenKind::Identifier(_)) {
+                    return Err(Box::new(ParserError::new(
+                        self.current_token.clone(),
+                        "Expect trait name".to_string(),
+                    )));
+                }
+                traits.push(Expr::Variable(Variable::new(self.current_token.clone())));
+                self.current_token = self.scanner.get_next_token()?;
+
+                if !matches!(self.current_token.kind(), TokenKind::Comma) {
+                    break;
+                }
+            }
+        }
+
+        let (methods, class_methods) = self.class_body("class")?;
+
+        Ok(Stmt::Class(Class::new(
+            name,
+            superclass,
+            traits,
+            methods,
+            class_methods,
+        )))
+    }
+
+    fn trait_declaration(&mut self) -> ParserResult<Stmt> {
+        let name = match self.current_token.kind() {
+            TokenKind::Identifier(_) => self.current_token.clone(),
+            _ => {
+                return Err(Box::new(ParserError::new(
+                    self.current_token.clone(),
+                    "Expect trait name".to_string(),
+                )))
+            }
+        };
+        self.current_token = self.scanner.get_next_token()?;
+
+        let (methods, class_methods) = self.class_body("trait")?;
+
+        Ok(Stmt::TraitStmt(TraitStmt::new(
+            name,
+            methods,
+            class_methods,
+        )))
+    }
+
+    /// Parses the braces and methods of a class or trait body, returning the instance
+    /// methods and the class methods separately.
+    fn class_body(&mut self, kind: &str) -> ParserResult<(Vec<Function>, Vec<Function>)> {
         if !matches!(self.current_token.kind(), TokenKind::LeftBrace) {
             return Err(Box::new(ParserError::new(
                 self.current_token.clone(),
-                "Expect '{' before class body.".to_string(),
+                format!("Expect '{{' before {} body.", kind),
             )));
         }
         self.current_token = self.scanner.get_next_token()?;
 
         let mut methods: Vec<Function> = Vec::new();
+        let mut class_methods: Vec<Function> = Vec::new();
         while !matches!(
             self.current_token.kind(),
             TokenKind::RightBrace | TokenKind::Eof
         ) {
-            let function_statement = self.function(FunctionKind::Function)?;
+            // Methods prefixed with 'class' are static, called on the class itself
+            let is_class_method = matches!(self.current_token.kind(), TokenKind::Class);
+            if is_class_method {
+                self.current_token = self.scanner.get_next_token()?;
+            }
+
+            let function_statement = self.function(FunctionKind::Method)?;
 
             if let Stmt::Function(function) = function_statement {
-                methods.push(function);
+                if is_class_method {
+                    class_methods.push(function);
+                } else {
+                    methods.push(function);
+                }
             }
         }
 
         if !matches!(self.current_token.kind(), TokenKind::RightBrace) {
             return Err(Box::new(ParserError::new(
                 self.current_token.clone(),
-                "Expect '}' after class body".to_string(),
+                format!("Expect '}}' after {} body", kind),
             )));
         }
         self.current_token = self.scanner.get_next_token()?;
 
-        Ok(Stmt::Class(Class::new(name, superclass, methods)))
+        Ok((methods, class_methods))
     }
 
     fn statement(&mut self) -> ParserResult<Stmt> {
@@ -354,6 +458,15 @@ impl Parser {
             // in the return_statement function
             return self.return_statement();
         }
+        if matches!(self.current_token.kind(), TokenKind::Throw) {
+            // Like return, the "throw" token is kept for error reporting
+            return self.throw_statement();
+        }
+        if matches!(self.current_token.kind(), TokenKind::Try) {
+            self.current_token = self.scanner.get_next_token()?;
+
+            return self.try_statement();
+        }
 
         self.expression_statement()
     }
@@ -428,10 +541,7 @@ impl Parser {
         Ok(Stmt::IfStmt(IfStmt::new(
             Box::new(condition),
             Box::new(then_branch),
-            match else_branch {
-                Some(stmt) => Some(Box::new(stmt)),
-                None => None,
-            },
+            else_branch.map(Box::new),
         )))
     }
 
@@ -542,7 +652,109 @@ impl Parser {
 
         Ok(Stmt::ReturnStmt(ReturnStmt::new(
             keyword,
-            value.map(|expr| Box::new(expr)),
+            value.map(Box::new),
+        )))
+    }
+
+    fn throw_statement(&mut self) -> ParserResult<Stmt> {
+        let keyword = self.current_token.clone();
+        self.current_token = self.scanner.get_next_token()?;
+
+        let value = self.expression()?;
+
+        if !matches!(self.current_token.kind(), TokenKind::Semicolon) {
+            return Err(Box::new(ParserError::new(
+                self.current_token.clone(),
+                "Expect ';' after thrown value".to_string(),
+            )));
+        }
+        self.current_token = self.scanner.get_next_token()?;
+
+        Ok(Stmt::ThrowStmt(ThrowStmt::new(keyword, Box::new(value))))
+    }
+
+    fn try_statement(&mut self) -> ParserResult<Stmt> {
+        if !matches!(self.current_token.kind(), TokenKind::LeftBrace) {
+            return Err(Box::new(ParserError::new(
+                self.current_token.clone(),
+                "Expect '{' after try".to_string(),
+            )));
+        }
+        self.current_token = self.scanner.get_next_token()?;
+
+        let try_block = self.block()?;
+
+        let (catch_name, catch_block) = match self.current_token.kind() {
+            TokenKind::Catch => {
+                self.current_token = self.scanner.get_next_token()?;
+
+                if !matches!(self.current_token.kind(), TokenKind::LeftParen) {
+                    return Err(Box::new(ParserError::new(
+                        self.current_token.clone(),
+                        "Expect '(' after catch".to_string(),
+                    )));
+                }
+                self.current_token = self.scanner.get_next_token()?;
+
+                if !matches!(self.current_token.kind(), TokenKind::Identifier(_)) {
+                    return Err(Box::new(ParserError::new(
+                        self.current_token.clone(),
+                        "Expect exception variable name".to_string(),
+                    )));
+                }
+                let name = self.current_token.clone();
+                self.current_token = self.scanner.get_next_token()?;
+
+                if !matches!(self.current_token.kind(), TokenKind::RightParen) {
+                    return Err(Box::new(ParserError::new(
+                        self.current_token.clone(),
+                        "Expect ')' after exception variable name".to_string(),
+                    )));
+                }
+                self.current_token = self.scanner.get_next_token()?;
+
+                if !matches!(self.current_token.kind(), TokenKind::LeftBrace) {
+                    return Err(Box::new(ParserError::new(
+                        self.current_token.clone(),
+                        "Expect '{' before catch body".to_string(),
+                    )));
+                }
+                self.current_token = self.scanner.get_next_token()?;
+
+                (Some(name), Some(self.block()?))
+            }
+            _ => (None, None),
+        };
+
+        let finally_block = match self.current_token.kind() {
+            TokenKind::Finally => {
+                self.current_token = self.scanner.get_next_token()?;
+
+                if !matches!(self.current_token.kind(), TokenKind::LeftBrace) {
+                    return Err(Box::new(ParserError::new(
+                        self.current_token.clone(),
+                        "Expect '{' after finally".to_string(),
+                    )));
+                }
+                self.current_token = self.scanner.get_next_token()?;
+
+                Some(self.block()?)
+            }
+            _ => None,
+        };
+
+        if catch_block.is_none() && finally_block.is_none() {
+            return Err(Box::new(ParserError::new(
+                self.current_token.clone(),
+                "Expect 'catch' or 'finally' after try block".to_string(),
+            )));
+        }
+
+        Ok(Stmt::TryStmt(TryStmt::new(
+            try_block,
+            catch_name,
+            catch_block,
+            finally_block,
         )))
     }
 
@@ -566,7 +778,7 @@ impl Parser {
     }
 
     fn assignment(&mut self) -> ParserResult<Expr> {
-        let expr = self.or()?;
+        let expr = self.conditional()?;
 
         match self.current_token.kind() {
             TokenKind::Equal => {
@@ -584,8 +796,34 @@ impl Parser {
                     Expr::Get(get) => {
                         Ok(Expr::Set(Set::new(get.object, get.name, Box::new(value))))
                     }
+                    Expr::Index(index) => Ok(Expr::SetIndex(SetIndex::new(
+                        index.object,
+                        index.bracket,
+                        index.index,
+                        Box::new(value),
+                    ))),
                     _ => {
-                        Lox::error(Box::new(RuntimeError::new(
+                        self.errors.push(Box::new(RuntimeError::new(
+                            equals,
+                            "Invalid assignment target".to_string(),
+                        )));
+                        Ok(expr)
+                    }
+                }
+            }
+            TokenKind::PlusEqual
+            | TokenKind::MinusEqual
+            | TokenKind::StarEqual
+            | TokenKind::SlashEqual => {
+                let equals = self.current_token.clone();
+                self.current_token = self.scanner.get_next_token()?;
+
+                let value = self.assignment()?;
+
+                match Self::compound_assignment(expr, Self::binary_operator(&equals), value) {
+                    Ok(expr) => Ok(expr),
+                    Err(expr) => {
+                        self.errors.push(Box::new(RuntimeError::new(
                             equals,
                             "Invalid assignment target".to_string(),
                         )));
@@ -597,6 +835,68 @@ impl Parser {
         }
     }
 
+    /// Builds `target <operator>= value`, or gives back the target if it can't be assigned to.
+    /// Variables become a plain assignment of `target <operator> value`, while properties
+    /// use `CompoundSet` so that the object expression is only evaluated once.
+    fn compound_assignment(target: Expr, operator: Token, value: Expr) -> Result<Expr, Expr> {
+        match target {
+            Expr::Variable(variable) => {
+                let current = Expr::Variable(variable.clone());
+                let value = Expr::Binary(Binary::new(Box::new(current), operator, Box::new(value)));
+
+                Ok(Expr::Assign(Assign::new(variable.name, Box::new(value))))
+            }
+            Expr::Get(get) => Ok(Expr::CompoundSet(CompoundSet::new(
+                get.object,
+                get.name,
+                operator,
+                Box::new(value),
+            ))),
+            _ => Err(target),
+        }
+    }
+
+    /// Maps a compound assignment or increment token to the binary operator it applies.
+    fn binary_operator(token: &Token) -> Token {
+        let kind = match token.kind() {
+            TokenKind::PlusEqual | TokenKind::PlusPlus => TokenKind::Plus,
+            TokenKind::MinusEqual | TokenKind::MinusMinus => TokenKind::Minus,
+            TokenKind::StarEqual => TokenKind::Star,
+            TokenKind::SlashEqual => TokenKind::Slash,
+            kind => kind.clone(),
+        };
+
+        Token::new(kind, *token.line())
+    }
+
+    fn conditional(&mut self) -> ParserResult<Expr> {
+        let condition = self.or()?;
+
+        if !matches!(self.current_token.kind(), TokenKind::Question) {
+            return Ok(condition);
+        }
+        self.current_token = self.scanner.get_next_token()?;
+
+        let then_branch = self.expression()?;
+
+        if !matches!(self.current_token.kind(), TokenKind::Colon) {
+            return Err(Box::new(ParserError::new(
+                self.current_token.clone(),
+                "Expect ':' after then branch of conditional expression".to_string(),
+            )));
+        }
+        self.current_token = self.scanner.get_next_token()?;
+
+        // Right associative, so `a ? b : c ? d : e` is `a ? b : (c ? d : e)`
+        let else_branch = self.conditional()?;
+
+        Ok(Expr::Conditional(Conditional::new(
+            Box::new(condition),
+            Box::new(then_branch),
+            Box::new(else_branch),
+        )))
+    }
+
     fn or(&mut self) -> ParserResult<Expr> {
         let mut expr = self.and()?;
 
@@ -676,7 +976,7 @@ impl Parser {
 
         while matches!(
             self.current_token.kind(),
-            TokenKind::Slash | TokenKind::Star
+            TokenKind::Slash | TokenKind::Star | TokenKind::Percent
         ) {
             let operator = self.current_token.clone();
             self.current_token = self.scanner.get_next_token()?;
@@ -697,8 +997,69 @@ impl Parser {
             let right = self.unary()?;
             return Ok(Expr::Unary(Unary::new(operator, Box::new(right))));
         }
+        if matches!(
+            self.current_token.kind(),
+            TokenKind::PlusPlus | TokenKind::MinusMinus
+        ) {
+            // Prefix increments are sugar for `target += 1` and `target -= 1`
+            let operator = self.current_token.clone();
+            self.current_token = self.scanner.get_next_token()?;
+            let target = self.unary()?;
+            let one = Expr::Literal(Literal::new(Token::new(
+                TokenKind::Number(1.0),
+                *operator.line(),
+            )));
+
+            return match Self::compound_assignment(target, Self::binary_operator(&operator), one) {
+                Ok(expr) => Ok(expr),
+                Err(_) => Err(Box::new(ParserError::new(
+                    operator,
+                    "Invalid increment target".to_string(),
+                ))),
+            };
+        }
 
-        self.call()
+        self.power()
+    }
+
+    fn power(&mut self) -> ParserResult<Expr> {
+        let expr = self.postfix()?;
+
+        if matches!(self.current_token.kind(), TokenKind::StarStar) {
+            let operator = self.current_token.clone();
+            self.current_token = self.scanner.get_next_token()?;
+            // The exponent may itself be negated or another power, making '**' right associative
+            let right = self.unary()?;
+            return Ok(Expr::Binary(Binary::new(
+                Box::new(expr),
+                operator,
+                Box::new(right),
+            )));
+        }
+
+        Ok(expr)
+    }
+
+    fn postfix(&mut self) -> ParserResult<Expr> {
+        let expr = self.call()?;
+
+        if matches!(
+            self.current_token.kind(),
+            TokenKind::PlusPlus | TokenKind::MinusMinus
+        ) {
+            let operator = self.current_token.clone();
+            if !matches!(expr, Expr::Variable(_) | Expr::Get(_)) {
+                return Err(Box::new(ParserError::new(
+                    operator,
+                    "Invalid increment target".to_string(),
+                )));
+            }
+            self.current_token = self.scanner.get_next_token()?;
+
+            return Ok(Expr::Postfix(Postfix::new(Box::new(expr), operator)));
+        }
+
+        Ok(expr)
     }
 
     fn call(&mut self) -> ParserResult<Expr> {
@@ -725,6 +1086,20 @@ impl Parser {
                     }
                     self.current_token = self.scanner.get_next_token()?;
                 }
+                TokenKind::LeftBracket => {
+                    self.current_token = self.scanner.get_next_token()?;
+                    let index = self.expression()?;
+                    if !matches!(self.current_token.kind(), TokenKind::RightBracket) {
+                        return Err(Box::new(ParserError::new(
+                            self.current_token.clone(),
+                            "Expect ']' after index".to_string(),
+                        )));
+                    }
+                    let bracket = self.current_token.clone();
+                    self.current_token = self.scanner.get_next_token()?;
+
+                    expr = Expr::Index(Index::new(Box::new(expr), bracket, Box::new(index)));
+                }
                 _ => break,
             }
         }
@@ -745,7 +1120,7 @@ impl Parser {
         while matches!(self.current_token.kind(), TokenKind::Comma) {
             self.current_token = self.scanner.get_next_token()?;
             if arguments.len() >= 255 {
-                Lox::error(Box::new(ParserError::new(
+                self.errors.push(Box::new(ParserError::new(
                     self.current_token.clone(),
                     "Can't have more than 255 arguments".to_string(),
                 )));
@@ -776,6 +1151,7 @@ impl Parser {
                 self.current_token = self.scanner.get_next_token()?;
                 Ok(Expr::Literal(Literal::new(token)))
             }
+            TokenKind::StringInterpolation(_) => self.interpolation(),
             TokenKind::LeftParen => {
                 self.current_token = self.scanner.get_next_token()?;
                 let expr = self.expression()?;
@@ -824,4 +1200,77 @@ impl Parser {
             ))),
         }
     }
+
+    /// Lowers `"a${x}b"` into `"a" + <stringified x> + "b"`.
+    fn interpolation(&mut self) -> ParserResult<Expr> {
+        let mut expr = self.string_part()?;
+
+        loop {
+            let token = self.current_token.clone();
+            let depth = self.scanner.interpolation_depth();
+            self.current_token = self.scanner.get_next_token()?;
+            // The scanner leaves the interpolation at its closing brace, so leaving it
+            // straight away means there was nothing between the braces
+            if self.scanner.interpolation_depth() < depth {
+                return Err(Box::new(ParserError::new(
+                    token,
+                    "Expect expression inside '${}'".to_string(),
+                )));
+            }
+
+            let value = self.expression()?;
+            let value = Expr::Stringify(Stringify::new(token, Box::new(value)));
+            expr = Self::concatenate(expr, value);
+
+            match self.current_token.kind() {
+                TokenKind::StringInterpolation(_) => {
+                    expr = Self::concatenate(expr, self.string_part()?);
+                }
+                TokenKind::String(_) => {
+                    expr = Self::concatenate(expr, self.string_part()?);
+                    self.current_token = self.scanner.get_next_token()?;
+                    break;
+                }
+                _ => {
+                    return Err(Box::new(ParserError::new(
+                        self.current_token.clone(),
+                        "Expect '}' after interpolated expression".to_string(),
+                    )))
+                }
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn string_part(&self) -> ParserResult<Expr> {
+        match self.current_token.kind() {
+            TokenKind::String(s) => Ok(Expr::Literal(Literal::new(Token::new(
+                TokenKind::String(s.clone()),
+                *self.current_token.line(),
+            )))),
+            TokenKind::StringInterpolation(s) => Ok(Expr::Literal(Literal::new(Token::new(
+                TokenKind::String(s.as_str().into()),
+                *self.current_token.line(),
+            )))),
+            _ => Err(Box::new(ParserError::new(
+                self.current_token.clone(),
+                "Expect string".to_string(),
+            ))),
+        }
+    }
+
+    fn concatenate(left: Expr, right: Expr) -> Expr {
+        let line = match &right {
+            Expr::Literal(literal) => *literal.value.line(),
+            Expr::Stringify(stringify) => *stringify.token.line(),
+            _ => 0,
+        };
+
+        Expr::Binary(Binary::new(
+            Box::new(left),
+            Token::new(TokenKind::Plus, line),
+            Box::new(right),
+        ))
+    }
 }
@@ -1,9 +1,15 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+    rc::Rc,
+};
 
 use crate::{
     ast::{Expr, ExprAccept, ExprVisitor, Stmt, StmtAccept, StmtVisitor},
+    intern::intern,
     interpreter::Interpreter,
-    lox::Lox,
     parser::ParserError,
     token::{Token, TokenKind},
 };
@@ -21,32 +27,140 @@ pub enum ClassKind {
     None,
     Class,
     Subclass,
+    Trait,
+}
+
+/// The method names a trait declares, used to find conflicts between the traits a
+/// class mixes in.
+struct TraitMethods {
+    methods: HashSet<Rc<str>>,
+    class_methods: HashSet<Rc<str>>,
+}
+
+/// A name in a local scope, and whether its initializer has finished.
+struct Local {
+    defined: bool,
+    // Index into `Symbols::declarations`, or `None` for the implicit `this` and `super`
+    declaration: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeclarationKind {
+    Variable,
+    Parameter,
+    Function,
+    Class,
+    Trait,
+    Method,
+    Getter,
+    ClassMethod,
+}
+
+impl Display for DeclarationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Variable => write!(f, "variable"),
+            Self::Parameter => write!(f, "parameter"),
+            Self::Function => write!(f, "function"),
+            Self::Class => write!(f, "class"),
+            Self::Trait => write!(f, "trait"),
+            Self::Method => write!(f, "method"),
+            Self::Getter => write!(f, "getter"),
+            Self::ClassMethod => write!(f, "class method"),
+        }
+    }
+}
+
+/// A name the program declares.
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub name: Token,
+    pub kind: DeclarationKind,
+    /// Declared outside every block and function, so it is looked up at runtime.
+    pub global: bool,
+    /// Parameter names, for functions and methods.
+    pub params: Vec<Token>,
+    /// Index of the class or trait that declares a method.
+    pub container: Option<usize>,
+}
+
+/// What a use of a name refers to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binding {
+    /// The declaration at this index in `Symbols::declarations`.
+    Local(usize),
+    /// A global, found by name when the code runs.
+    Global,
+    /// A property or method, found by name on the object when the code runs.
+    Property,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: Token,
+    pub binding: Binding,
+}
+
+/// Every declaration and use of a name seen by the resolver, in the order it visits
+/// them. The interpreter doesn't need these; they are kept for editor tooling.
+#[derive(Debug, Default, Clone)]
+pub struct Symbols {
+    pub declarations: Vec<Declaration>,
+    pub references: Vec<Reference>,
 }
 
 pub struct Resolver {
-    interpreter: Rc<RefCell<Interpreter>>,
-    scopes: Vec<HashMap<String, bool>>,
+    // Told the scope distance of every local variable, if the code is going to run
+    interpreter: Option<Rc<RefCell<Interpreter>>>,
+    scopes: Vec<HashMap<Rc<str>, Local>>,
     current_function: FunctionKind,
     current_class: ClassKind,
+    traits: HashMap<Rc<str>, TraitMethods>,
+    errors: Vec<Box<dyn Error>>,
+    symbols: Symbols,
 }
 
 impl Resolver {
     pub fn new(interpreter: Rc<RefCell<Interpreter>>) -> Self {
+        Self::with_interpreter(Some(interpreter))
+    }
+
+    /// A resolver that only checks the code and records its symbols, for tools such
+    /// as the language server that never run it.
+    pub fn without_interpreter() -> Self {
+        Self::with_interpreter(None)
+    }
+
+    fn with_interpreter(interpreter: Option<Rc<RefCell<Interpreter>>>) -> Self {
         Self {
             interpreter,
             scopes: Vec::new(),
             current_function: FunctionKind::None,
             current_class: ClassKind::None,
+            traits: HashMap::new(),
+            errors: Vec::new(),
+            symbols: Symbols::default(),
         }
     }
 
+    /// The errors found while resolving, such as returning from top-level code.
+    pub fn errors(&self) -> &[Box<dyn Error>] {
+        &self.errors
+    }
+
+    pub fn symbols(&self) -> &Symbols {
+        &self.symbols
+    }
+
     pub fn resolve(&mut self, statements: &Vec<Stmt>) {
         for statement in statements {
             statement.accept(self);
         }
     }
 
-    fn declare(&mut self, name: &Token) {
+    fn declare(&mut self, name: &Token, kind: DeclarationKind) {
+        let declaration = self.record(name, kind);
+
         if self.scopes.is_empty() {
             return;
         }
@@ -54,13 +168,19 @@ impl Resolver {
         let scope = self.scopes.last_mut().unwrap();
         if let TokenKind::Identifier(id) = name.kind() {
             if scope.contains_key(id) {
-                Lox::error(Box::new(ParserError::new(
+                self.errors.push(Box::new(ParserError::new(
                     name.clone(),
                     "Already a variable with this name in this scope.".to_string(),
                 )));
             }
 
-            scope.insert(id.clone(), false);
+            scope.insert(
+                id.clone(),
+                Local {
+                    defined: false,
+                    declaration: Some(declaration),
+                },
+            );
         }
     }
 
@@ -71,17 +191,151 @@ impl Resolver {
 
         let scope = self.scopes.last_mut().unwrap();
         if let TokenKind::Identifier(id) = name.kind() {
-            scope.insert(id.clone(), true);
+            if let Some(local) = scope.get_mut(id) {
+                local.defined = true;
+            }
+        }
+    }
+
+    /// Adds a declaration to the symbol table and returns its index.
+    fn record(&mut self, name: &Token, kind: DeclarationKind) -> usize {
+        self.symbols.declarations.push(Declaration {
+            name: name.clone(),
+            kind,
+            global: self.scopes.is_empty(),
+            params: Vec::new(),
+            container: None,
+        });
+
+        self.symbols.declarations.len() - 1
+    }
+
+    /// Records a use of `name`, bound to the innermost local declaration of it or
+    /// else to a global.
+    fn reference(&mut self, name: &Token) {
+        let TokenKind::Identifier(id) = name.kind() else {
+            return;
+        };
+        let binding = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(id))
+            .map_or(Binding::Global, |local| match local.declaration {
+                Some(declaration) => Binding::Local(declaration),
+                None => Binding::Global,
+            });
+
+        self.symbols.references.push(Reference {
+            name: name.clone(),
+            binding,
+        });
+    }
+
+    fn property(&mut self, name: &Token) {
+        self.symbols.references.push(Reference {
+            name: name.clone(),
+            binding: Binding::Property,
+        });
+    }
+
+    /// Records a method of the class or trait declared at `container`.
+    fn method(&mut self, method: &crate::ast::Function, kind: DeclarationKind, container: usize) {
+        let kind = if method.is_getter {
+            DeclarationKind::Getter
+        } else {
+            kind
+        };
+
+        self.symbols.declarations.push(Declaration {
+            name: method.name.clone(),
+            kind,
+            global: false,
+            params: method.params.clone(),
+            container: Some(container),
+        });
+    }
+
+    /// Scope holding only an implicit name, such as `this` in a class body.
+    fn implicit_scope(name: &str) -> HashMap<Rc<str>, Local> {
+        HashMap::from([(
+            intern(name),
+            Local {
+                defined: true,
+                declaration: None,
+            },
+        )])
+    }
+
+    /// Reports methods that more than one of a class's traits define, unless the class
+    /// overrides them itself. Methods are keyed by whether they are class methods, since
+    /// those live in a separate table.
+    fn check_trait_conflicts(&mut self, class: &crate::ast::Class) {
+        let overridden: HashSet<(bool, Rc<str>)> = class
+            .methods
+            .iter()
+            .map(|method| (false, function_name(method)))
+            .chain(
+                class
+                    .class_methods
+                    .iter()
+                    .map(|method| (true, function_name(method))),
+            )
+            .collect();
+        let mut defined_by: HashMap<(bool, Rc<str>), Rc<str>> = HashMap::new();
+        let mut conflicts: Vec<Box<dyn Error>> = Vec::new();
+
+        for trait_expr in &class.traits {
+            let (token, trait_name) = match trait_expr {
+                Expr::Variable(var) => match var.name.kind() {
+                    TokenKind::Identifier(id) => (&var.name, id),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            let Some(declared) = self.traits.get(trait_name) else {
+                continue;
+            };
+
+            let methods = declared.methods.iter().map(|name| (false, name.clone()));
+            let class_methods = declared
+                .class_methods
+                .iter()
+                .map(|name| (true, name.clone()));
+            for key in methods.chain(class_methods) {
+                if overridden.contains(&key) {
+                    continue;
+                }
+
+                match defined_by.get(&key) {
+                    Some(other) if other != trait_name => {
+                        conflicts.push(Box::new(ParserError::new(
+                            token.clone(),
+                            format!(
+                            "Traits '{}' and '{}' both define '{}', so the class must override it.",
+                            other, trait_name, key.1
+                        ),
+                        )))
+                    }
+                    _ => {
+                        defined_by.insert(key, trait_name.clone());
+                    }
+                }
+            }
         }
+
+        self.errors.extend(conflicts);
     }
 
     fn resolve_local(&mut self, expr: &Expr, name: &Token) {
         for (i, scope) in self.scopes.iter().enumerate().rev() {
             if let TokenKind::Identifier(id) = name.kind() {
                 if scope.contains_key(id) {
-                    self.interpreter
-                        .borrow_mut()
-                        .resolve(expr, self.scopes.len() - 1 - i);
+                    if let Some(interpreter) = &self.interpreter {
+                        interpreter
+                            .borrow_mut()
+                            .resolve(expr, self.scopes.len() - 1 - i);
+                    }
                     return;
                 }
             }
@@ -95,7 +349,7 @@ impl Resolver {
         self.scopes.push(HashMap::new());
 
         for parameter in &function.params {
-            self.declare(parameter);
+            self.declare(parameter, DeclarationKind::Parameter);
             self.define(parameter);
         }
 
@@ -115,9 +369,9 @@ impl ExprVisitor for Resolver {
     fn visit_variable(&mut self, variable: &crate::ast::Variable) -> Self::Result {
         if !self.scopes.is_empty() {
             if let TokenKind::Identifier(id) = variable.name.kind() {
-                if let Some(x) = self.scopes.last().unwrap().get(id) {
-                    if *x == false {
-                        Lox::error(Box::new(ParserError::new(
+                if let Some(local) = self.scopes.last().unwrap().get(id) {
+                    if !local.defined {
+                        self.errors.push(Box::new(ParserError::new(
                             variable.name.clone(),
                             "Can't read local variable in its own initializer".to_string(),
                         )));
@@ -126,11 +380,13 @@ impl ExprVisitor for Resolver {
             }
         }
 
+        self.reference(&variable.name);
         self.resolve_local(&Expr::Variable(variable.clone()), &variable.name);
     }
 
     fn visit_assign(&mut self, assign: &crate::ast::Assign) -> Self::Result {
         assign.value.accept(self);
+        self.reference(&assign.name);
         self.resolve_local(&Expr::Assign(assign.clone()), &assign.name);
     }
 
@@ -164,16 +420,51 @@ impl ExprVisitor for Resolver {
 
     fn visit_get(&mut self, get: &crate::ast::Get) -> Self::Result {
         get.object.accept(self);
+        self.property(&get.name);
     }
 
     fn visit_set(&mut self, set: &crate::ast::Set) -> Self::Result {
         set.value.accept(self);
         set.object.accept(self);
+        self.property(&set.name);
+    }
+
+    fn visit_index(&mut self, index: &crate::ast::Index) -> Self::Result {
+        index.object.accept(self);
+        index.index.accept(self);
+    }
+
+    fn visit_setindex(&mut self, setindex: &crate::ast::SetIndex) -> Self::Result {
+        setindex.value.accept(self);
+        setindex.object.accept(self);
+        setindex.index.accept(self);
+    }
+
+    fn visit_stringify(&mut self, stringify: &crate::ast::Stringify) -> Self::Result {
+        stringify.expression.accept(self);
+    }
+
+    fn visit_conditional(&mut self, conditional: &crate::ast::Conditional) -> Self::Result {
+        conditional.condition.accept(self);
+        conditional.then_branch.accept(self);
+        conditional.else_branch.accept(self);
+    }
+
+    fn visit_compoundset(&mut self, compoundset: &crate::ast::CompoundSet) -> Self::Result {
+        compoundset.value.accept(self);
+        compoundset.object.accept(self);
+        self.property(&compoundset.name);
+    }
+
+    fn visit_postfix(&mut self, postfix: &crate::ast::Postfix) -> Self::Result {
+        // The target is resolved as a variable or property read; the interpreter writes
+        // the new value back through the same resolution
+        postfix.target.accept(self);
     }
 
     fn visit_this(&mut self, this: &crate::ast::This) -> Self::Result {
         if self.current_class == ClassKind::None {
-            Lox::error(Box::new(ParserError::new(
+            self.errors.push(Box::new(ParserError::new(
                 this.keyword.clone(),
                 "Can't use 'this' outside of a class.".to_string(),
             )));
@@ -182,31 +473,34 @@ impl ExprVisitor for Resolver {
 
         self.resolve_local(
             &Expr::This(this.clone()),
-            &Token::new(
-                TokenKind::Identifier("this".to_string()),
-                this.keyword.line().clone(),
-            ),
+            &Token::new(TokenKind::Identifier(intern("this")), *this.keyword.line()),
         );
     }
 
     fn visit_superexpr(&mut self, superexpr: &crate::ast::SuperExpr) -> Self::Result {
         if self.current_class == ClassKind::None {
-            Lox::error(Box::new(ParserError::new(
+            self.errors.push(Box::new(ParserError::new(
                 superexpr.keyword.clone(),
                 "Can't use 'super' outside of a class.".to_string(),
             )))
         } else if self.current_class == ClassKind::Class {
-            Lox::error(Box::new(ParserError::new(
+            self.errors.push(Box::new(ParserError::new(
                 superexpr.keyword.clone(),
                 "Can't use 'super' in a class with no superclass".to_string(),
             )))
+        } else if self.current_class == ClassKind::Trait {
+            self.errors.push(Box::new(ParserError::new(
+                superexpr.keyword.clone(),
+                "Can't use 'super' in a trait.".to_string(),
+            )))
         }
 
+        self.property(&superexpr.method);
         self.resolve_local(
             &Expr::SuperExpr(superexpr.clone()),
             &Token::new(
-                TokenKind::Identifier("super".to_string()),
-                superexpr.keyword.line().clone(),
+                TokenKind::Identifier(intern("super")),
+                *superexpr.keyword.line(),
             ),
         );
     }
@@ -226,7 +520,7 @@ impl StmtVisitor for Resolver {
     }
 
     fn visit_var(&mut self, var: &crate::ast::Var) -> Self::Result {
-        self.declare(&var.name);
+        self.declare(&var.name, DeclarationKind::Variable);
 
         if let Some(initializer) = &var.initializer {
             initializer.accept(self);
@@ -236,8 +530,9 @@ impl StmtVisitor for Resolver {
     }
 
     fn visit_function(&mut self, function: &crate::ast::Function) -> Self::Result {
-        self.declare(&function.name);
+        self.declare(&function.name, DeclarationKind::Function);
         self.define(&function.name);
+        self.symbols.declarations.last_mut().unwrap().params = function.params.clone();
 
         self.resolve_function(function, FunctionKind::Function);
     }
@@ -260,7 +555,7 @@ impl StmtVisitor for Resolver {
 
     fn visit_returnstmt(&mut self, returnstmt: &crate::ast::ReturnStmt) -> Self::Result {
         if self.current_function == FunctionKind::None {
-            Lox::error(Box::new(ParserError::new(
+            self.errors.push(Box::new(ParserError::new(
                 returnstmt.keyword.clone(),
                 "Can't return from top-level code.".to_string(),
             )))
@@ -268,7 +563,7 @@ impl StmtVisitor for Resolver {
 
         if let Some(value) = &returnstmt.value {
             if self.current_function == FunctionKind::Initializer {
-                Lox::error(Box::new(ParserError::new(
+                self.errors.push(Box::new(ParserError::new(
                     returnstmt.keyword.clone(),
                     "Can't return a value from an initializer.".to_string(),
                 )));
@@ -277,6 +572,36 @@ impl StmtVisitor for Resolver {
         }
     }
 
+    fn visit_throwstmt(&mut self, throwstmt: &crate::ast::ThrowStmt) -> Self::Result {
+        throwstmt.value.accept(self);
+    }
+
+    fn visit_trystmt(&mut self, trystmt: &crate::ast::TryStmt) -> Self::Result {
+        self.scopes.push(HashMap::new());
+        for statement in &trystmt.try_block {
+            statement.accept(self);
+        }
+        self.scopes.pop();
+
+        if let (Some(name), Some(catch_block)) = (&trystmt.catch_name, &trystmt.catch_block) {
+            self.scopes.push(HashMap::new());
+            self.declare(name, DeclarationKind::Variable);
+            self.define(name);
+            for statement in catch_block {
+                statement.accept(self);
+            }
+            self.scopes.pop();
+        }
+
+        if let Some(finally_block) = &trystmt.finally_block {
+            self.scopes.push(HashMap::new());
+            for statement in finally_block {
+                statement.accept(self);
+            }
+            self.scopes.pop();
+        }
+    }
+
     fn visit_whilestmt(&mut self, whilestmt: &crate::ast::WhileStmt) -> Self::Result {
         whilestmt.condition.accept(self);
         whilestmt.body.accept(self);
@@ -286,19 +611,25 @@ impl StmtVisitor for Resolver {
         let enclosing_class = self.current_class.clone();
         self.current_class = ClassKind::Class;
 
-        self.declare(&class.name);
+        self.declare(&class.name, DeclarationKind::Class);
         self.define(&class.name);
+        let container = self.symbols.declarations.len() - 1;
+
+        for trait_expr in &class.traits {
+            trait_expr.accept(self);
+        }
+        self.check_trait_conflicts(class);
 
         if let Some(superclass) = &class.superclass {
             let class_name = match class.name.kind() {
                 TokenKind::Identifier(id) => id.clone(),
-                _ => "".to_string(),
+                _ => "".into(),
             };
             match *superclass.clone() {
                 Expr::Variable(var) => match var.name.kind() {
                     TokenKind::Identifier(id) => {
                         if class_name == id.clone() {
-                            Lox::error(Box::new(ParserError::new(
+                            self.errors.push(Box::new(ParserError::new(
                                 var.name.clone(),
                                 "A class can't inherit from itself.".to_string(),
                             )));
@@ -312,23 +643,15 @@ impl StmtVisitor for Resolver {
             self.current_class = ClassKind::Subclass;
             superclass.accept(self);
 
-            self.scopes.push(HashMap::new());
-            self.scopes
-                .last_mut()
-                .unwrap()
-                .insert("super".to_string(), true);
+            self.scopes.push(Self::implicit_scope("super"));
         }
 
-        self.scopes.push(HashMap::new());
-        self.scopes
-            .last_mut()
-            .unwrap()
-            .insert("this".to_string(), true);
+        self.scopes.push(Self::implicit_scope("this"));
 
         for method in &class.methods {
             let kind = match method.name.kind() {
                 TokenKind::Identifier(id) => {
-                    if id == "init" {
+                    if &**id == "init" {
                         FunctionKind::Initializer
                     } else {
                         FunctionKind::Method
@@ -336,9 +659,22 @@ impl StmtVisitor for Resolver {
                 }
                 _ => FunctionKind::Method,
             };
+            if kind == FunctionKind::Initializer && method.is_getter {
+                self.errors.push(Box::new(ParserError::new(
+                    method.name.clone(),
+                    "An initializer can't be a getter.".to_string(),
+                )));
+            }
+            self.method(method, DeclarationKind::Method, container);
             self.resolve_function(method, kind);
         }
 
+        // Inside a class method, 'this' is the class
+        for method in &class.class_methods {
+            self.method(method, DeclarationKind::ClassMethod, container);
+            self.resolve_function(method, FunctionKind::Method);
+        }
+
         self.scopes.pop();
 
         if class.superclass.is_some() {
@@ -347,4 +683,52 @@ impl StmtVisitor for Resolver {
 
         self.current_class = enclosing_class;
     }
+
+    fn visit_traitstmt(&mut self, traitstmt: &crate::ast::TraitStmt) -> Self::Result {
+        let enclosing_class = self.current_class.clone();
+        self.current_class = ClassKind::Trait;
+
+        self.declare(&traitstmt.name, DeclarationKind::Trait);
+        self.define(&traitstmt.name);
+        let container = self.symbols.declarations.len() - 1;
+
+        if let TokenKind::Identifier(id) = traitstmt.name.kind() {
+            self.traits.insert(
+                id.clone(),
+                TraitMethods {
+                    methods: traitstmt.methods.iter().map(function_name).collect(),
+                    class_methods: traitstmt.class_methods.iter().map(function_name).collect(),
+                },
+            );
+        }
+
+        self.scopes.push(Self::implicit_scope("this"));
+
+        for method in &traitstmt.methods {
+            self.method(method, DeclarationKind::Method, container);
+        }
+        for method in &traitstmt.class_methods {
+            self.method(method, DeclarationKind::ClassMethod, container);
+        }
+        for method in traitstmt.methods.iter().chain(&traitstmt.class_methods) {
+            if &*function_name(method) == "init" {
+                self.errors.push(Box::new(ParserError::new(
+                    method.name.clone(),
+                    "A trait can't define an initializer.".to_string(),
+                )));
+            }
+            self.resolve_function(method, FunctionKind::Method);
+        }
+
+        self.scopes.pop();
+
+        self.current_class = enclosing_class;
+    }
+}
+
+fn function_name(function: &crate::ast::Function) -> Rc<str> {
+    match function.name.kind() {
+        TokenKind::Identifier(id) => id.clone(),
+        _ => "".into(),
+    }
 }
@@ -1,6 +1,9 @@
 use std::{error::Error, fmt::Display};
 
-use crate::token::{Token, TokenKind};
+use crate::{
+    intern::intern,
+    token::{Token, TokenKind},
+};
 
 #[derive(Debug)]
 pub struct ScannerError {
@@ -17,6 +20,14 @@ impl ScannerError {
             message,
         }
     }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
 }
 
 impl Error for ScannerError {}
@@ -35,7 +46,12 @@ pub struct Scanner {
     source: Vec<char>,
     index: usize,
     line: usize,
+    // Index of the first character of the current line, used to compute token columns
+    line_start: usize,
+    token_column: usize,
     current_char: Option<char>,
+    // Brace depth for each string interpolation currently being scanned
+    interpolations: Vec<usize>,
 }
 
 impl Scanner {
@@ -44,7 +60,10 @@ impl Scanner {
             source: source.chars().collect(),
             index: 0,
             line: 1,
+            line_start: 0,
+            token_column: 1,
             current_char: None,
+            interpolations: Vec::new(),
         };
 
         if !new_self.source.is_empty() {
@@ -54,7 +73,22 @@ impl Scanner {
         new_self
     }
 
+    /// How many string interpolations the scanner is inside.
+    pub fn interpolation_depth(&self) -> usize {
+        self.interpolations.len()
+    }
+
     pub fn get_next_token(&mut self) -> Result<Token, ScannerError> {
+        let mut token = self.scan_token()?;
+        token.set_column(self.token_column);
+
+        Ok(token)
+    }
+
+    fn scan_token(&mut self) -> Result<Token, ScannerError> {
+        // Whitespace and comments recurse, so this ends up at the start of the actual token
+        self.token_column = self.index - self.line_start + 1;
+
         if self.current_char.is_none() {
             return Ok(Token::new(TokenKind::Eof, self.line));
         }
@@ -69,12 +103,34 @@ impl Scanner {
                 Ok(Token::new(TokenKind::RightParen, self.line))
             }
             '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
                 self.get_next_character();
                 Ok(Token::new(TokenKind::LeftBrace, self.line))
             }
             '}' => {
                 self.get_next_character();
-                Ok(Token::new(TokenKind::RightBrace, self.line))
+                match self.interpolations.last_mut() {
+                    // This brace closes an interpolated expression, so the string continues
+                    Some(0) => {
+                        self.interpolations.pop();
+                        self.get_string()
+                    }
+                    Some(depth) => {
+                        *depth -= 1;
+                        Ok(Token::new(TokenKind::RightBrace, self.line))
+                    }
+                    None => Ok(Token::new(TokenKind::RightBrace, self.line)),
+                }
+            }
+            '[' => {
+                self.get_next_character();
+                Ok(Token::new(TokenKind::LeftBracket, self.line))
+            }
+            ']' => {
+                self.get_next_character();
+                Ok(Token::new(TokenKind::RightBracket, self.line))
             }
             ',' => {
                 self.get_next_character();
@@ -84,22 +140,70 @@ impl Scanner {
                 self.get_next_character();
                 Ok(Token::new(TokenKind::Dot, self.line))
             }
-            '-' => {
+            '-' => match self.peek_next_character() {
+                Some('=') => {
+                    self.get_next_character();
+                    self.get_next_character(); // Once for '-' and once for '='
+                    Ok(Token::new(TokenKind::MinusEqual, self.line))
+                }
+                Some('-') => {
+                    self.get_next_character();
+                    self.get_next_character(); // Once for each '-'
+                    Ok(Token::new(TokenKind::MinusMinus, self.line))
+                }
+                _ => {
+                    self.get_next_character();
+                    Ok(Token::new(TokenKind::Minus, self.line))
+                }
+            },
+            '+' => match self.peek_next_character() {
+                Some('=') => {
+                    self.get_next_character();
+                    self.get_next_character(); // Once for '+' and once for '='
+                    Ok(Token::new(TokenKind::PlusEqual, self.line))
+                }
+                Some('+') => {
+                    self.get_next_character();
+                    self.get_next_character(); // Once for each '+'
+                    Ok(Token::new(TokenKind::PlusPlus, self.line))
+                }
+                _ => {
+                    self.get_next_character();
+                    Ok(Token::new(TokenKind::Plus, self.line))
+                }
+            },
+            ';' => {
                 self.get_next_character();
-                Ok(Token::new(TokenKind::Minus, self.line))
+                Ok(Token::new(TokenKind::Semicolon, self.line))
             }
-            '+' => {
+            '%' => {
                 self.get_next_character();
-                Ok(Token::new(TokenKind::Plus, self.line))
+                Ok(Token::new(TokenKind::Percent, self.line))
             }
-            ';' => {
+            '?' => {
                 self.get_next_character();
-                Ok(Token::new(TokenKind::Semicolon, self.line))
+                Ok(Token::new(TokenKind::Question, self.line))
             }
-            '*' => {
+            ':' => {
                 self.get_next_character();
-                Ok(Token::new(TokenKind::Star, self.line))
+                Ok(Token::new(TokenKind::Colon, self.line))
             }
+            '*' => match self.peek_next_character() {
+                Some('=') => {
+                    self.get_next_character();
+                    self.get_next_character(); // Once for '*' and once for '='
+                    Ok(Token::new(TokenKind::StarEqual, self.line))
+                }
+                Some('*') => {
+                    self.get_next_character();
+                    self.get_next_character(); // Once for each '*'
+                    Ok(Token::new(TokenKind::StarStar, self.line))
+                }
+                _ => {
+                    self.get_next_character();
+                    Ok(Token::new(TokenKind::Star, self.line))
+                }
+            },
             '!' => match self.peek_next_character() {
                 Some('=') => {
                     self.get_next_character();
@@ -149,7 +253,12 @@ impl Scanner {
                     self.get_next_character();
                     self.get_next_character(); // Once for each '/'
                     self.skip_comment();
-                    self.get_next_token()
+                    self.scan_token()
+                }
+                Some('=') => {
+                    self.get_next_character();
+                    self.get_next_character(); // Once for '/' and once for '='
+                    Ok(Token::new(TokenKind::SlashEqual, self.line))
                 }
                 _ => {
                     self.get_next_character();
@@ -159,11 +268,12 @@ impl Scanner {
             '\n' => {
                 self.line += 1;
                 self.get_next_character();
-                self.get_next_token()
+                self.line_start = self.index;
+                self.scan_token()
             }
             ' ' | '\r' | '\t' => {
                 self.get_next_character();
-                self.get_next_token()
+                self.scan_token()
             }
             '"' => {
                 self.get_next_character();
@@ -213,25 +323,132 @@ impl Scanner {
 
     fn get_string(&mut self) -> Result<Token, ScannerError> {
         let mut string_chars: Vec<char> = Vec::new();
+        // Invalid escapes are reported once the whole string is consumed so that
+        // scanning resumes after the closing quote
+        let mut escape_error: Option<ScannerError> = None;
+
+        loop {
+            match self.current_char {
+                None => {
+                    return Err(ScannerError::new(
+                        self.line,
+                        None,
+                        "Unterminated string".to_string(),
+                    ))
+                }
+                Some('"') => break,
+                Some('\\') => {
+                    self.get_next_character();
+                    match self.get_escape_sequence() {
+                        Ok(c) => string_chars.push(c),
+                        Err(err) => {
+                            escape_error.get_or_insert(err);
+                        }
+                    }
+                }
+                Some('$') if self.peek_next_character() == Some('{') => {
+                    self.get_next_character();
+                    self.get_next_character(); // Once for '$' and once for '{'
+                    self.interpolations.push(0);
+
+                    if let Some(err) = escape_error {
+                        return Err(err);
+                    }
+
+                    let token_string: String = string_chars.into_iter().collect();
+                    return Ok(Token::new(
+                        TokenKind::StringInterpolation(token_string),
+                        self.line,
+                    ));
+                }
+                Some(c) => {
+                    string_chars.push(c);
+                    self.get_next_character();
+                    if c == '\n' {
+                        self.line += 1;
+                        self.line_start = self.index;
+                    }
+                }
+            }
+        }
 
-        while self.current_char.is_some() && self.current_char.unwrap() != '"' {
-            string_chars.push(self.current_char.unwrap());
-            self.get_next_character();
+        self.get_next_character();
+
+        if let Some(err) = escape_error {
+            return Err(err);
         }
 
-        if self.current_char.is_none() {
+        let token_string: String = string_chars.into_iter().collect();
+
+        Ok(Token::new(
+            TokenKind::String(token_string.into()),
+            self.line,
+        ))
+    }
+
+    fn get_escape_sequence(&mut self) -> Result<char, ScannerError> {
+        let escaped = match self.current_char {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some('"') => '"',
+            Some('$') => '$',
+            Some('u') => {
+                self.get_next_character();
+                return self.get_unicode_escape();
+            }
+            c => {
+                self.get_next_character();
+                return Err(ScannerError::new(
+                    self.line,
+                    c,
+                    "Invalid escape sequence".to_string(),
+                ));
+            }
+        };
+        self.get_next_character();
+
+        Ok(escaped)
+    }
+
+    fn get_unicode_escape(&mut self) -> Result<char, ScannerError> {
+        if self.current_char != Some('{') {
             return Err(ScannerError::new(
                 self.line,
-                None,
-                "Unterminated string".to_string(),
+                self.current_char,
+                "Expect '{' after '\\u'".to_string(),
             ));
         }
-
         self.get_next_character();
 
-        let token_string: String = string_chars.into_iter().collect();
+        let mut digits: Vec<char> = Vec::new();
+        while let Some(c) = self.current_char {
+            if !c.is_ascii_hexdigit() {
+                break;
+            }
+            digits.push(c);
+            self.get_next_character();
+        }
 
-        Ok(Token::new(TokenKind::String(token_string), self.line))
+        if self.current_char != Some('}') || digits.is_empty() || digits.len() > 6 {
+            return Err(ScannerError::new(
+                self.line,
+                self.current_char,
+                "Expect 1 to 6 hex digits and '}' in unicode escape".to_string(),
+            ));
+        }
+        self.get_next_character();
+
+        let code = u32::from_str_radix(&digits.into_iter().collect::<String>(), 16).unwrap();
+        char::from_u32(code).ok_or_else(|| {
+            ScannerError::new(
+                self.line,
+                None,
+                format!("Invalid unicode code point {:X}", code),
+            )
+        })
     }
 
     fn get_number(&mut self) -> Token {
@@ -292,9 +509,11 @@ impl Scanner {
 
         match id.as_str() {
             "and" => Token::new(TokenKind::And, self.line),
+            "catch" => Token::new(TokenKind::Catch, self.line),
             "class" => Token::new(TokenKind::Class, self.line),
             "else" => Token::new(TokenKind::Else, self.line),
             "false" => Token::new(TokenKind::False, self.line),
+            "finally" => Token::new(TokenKind::Finally, self.line),
             "for" => Token::new(TokenKind::For, self.line),
             "fun" => Token::new(TokenKind::Fun, self.line),
             "if" => Token::new(TokenKind::If, self.line),
@@ -304,10 +523,14 @@ impl Scanner {
             "return" => Token::new(TokenKind::Return, self.line),
             "super" => Token::new(TokenKind::Super, self.line),
             "this" => Token::new(TokenKind::This, self.line),
+            "throw" => Token::new(TokenKind::Throw, self.line),
+            "trait" => Token::new(TokenKind::Trait, self.line),
             "true" => Token::new(TokenKind::True, self.line),
+            "try" => Token::new(TokenKind::Try, self.line),
             "var" => Token::new(TokenKind::Var, self.line),
             "while" => Token::new(TokenKind::While, self.line),
-            _ => Token::new(TokenKind::Identifier(id), self.line),
+            "with" => Token::new(TokenKind::With, self.line),
+            _ => Token::new(TokenKind::Identifier(intern(&id)), self.line),
         }
     }
 }
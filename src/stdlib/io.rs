@@ -0,0 +1,127 @@
+//! Natives for reading standard input and working with files. The file natives
+//! check `Interpreter::filesystem_access` on every call.
+
+use std::{
+    error::Error,
+    fs::{self, OpenOptions},
+    io::{stdin, Write},
+};
+
+use crate::{
+    environment::Environment,
+    gc,
+    interpreter::{Interpreter, LoxValue, NativeError},
+    stdlib::{native, string_argument},
+};
+
+pub fn define(globals: &mut Environment) {
+    globals.define(
+        "readLine".to_string(),
+        native("readLine", 0..=0, |interpreter, _| {
+            let mut line = String::new();
+            let bytes_read = stdin()
+                .read_line(&mut line)
+                .map_err(|err| io_error("read from stdin", err))?;
+
+            // End of input is signalled with nil rather than an error
+            if bytes_read == 0 {
+                return Ok(LoxValue::Nil);
+            }
+
+            let line = line.strip_suffix('\n').unwrap_or(&line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            interpreter.reserve(line.len())?;
+            Ok(LoxValue::String(line.into()))
+        }),
+    );
+    globals.define(
+        "readFile".to_string(),
+        native("readFile", 1..=1, |interpreter, arguments| {
+            let path = path_argument(interpreter, "readFile", &arguments[0])?;
+            // Errors reading the size are left to the read, which reports them
+            if let Ok(metadata) = fs::metadata(&path) {
+                interpreter.reserve(usize::try_from(metadata.len()).unwrap_or(usize::MAX))?;
+            }
+            let contents = fs::read_to_string(&path)
+                .map_err(|err| io_error(&format!("read '{}'", path), err))?;
+
+            Ok(LoxValue::String(contents.into()))
+        }),
+    );
+    globals.define(
+        "writeFile".to_string(),
+        native("writeFile", 2..=2, |interpreter, arguments| {
+            let path = path_argument(interpreter, "writeFile", &arguments[0])?;
+            let text = string_argument("writeFile", &arguments[1])?;
+            fs::write(&path, &*text).map_err(|err| io_error(&format!("write '{}'", path), err))?;
+
+            Ok(LoxValue::Nil)
+        }),
+    );
+    globals.define(
+        "appendFile".to_string(),
+        native("appendFile", 2..=2, |interpreter, arguments| {
+            let path = path_argument(interpreter, "appendFile", &arguments[0])?;
+            let text = string_argument("appendFile", &arguments[1])?;
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .and_then(|mut file| file.write_all(text.as_bytes()))
+                .map_err(|err| io_error(&format!("append to '{}'", path), err))?;
+
+            Ok(LoxValue::Nil)
+        }),
+    );
+    globals.define(
+        "fileExists".to_string(),
+        native("fileExists", 1..=1, |interpreter, arguments| {
+            let path = path_argument(interpreter, "fileExists", &arguments[0])?;
+
+            Ok(LoxValue::Boolean(fs::metadata(path).is_ok()))
+        }),
+    );
+    globals.define(
+        "listDir".to_string(),
+        native("listDir", 1..=1, |interpreter, arguments| {
+            let path = path_argument(interpreter, "listDir", &arguments[0])?;
+            let mut names = fs::read_dir(&path)
+                .and_then(|entries| {
+                    entries
+                        .map(|entry| {
+                            entry.map(|entry| entry.file_name().to_string_lossy().into_owned())
+                        })
+                        .collect::<Result<Vec<String>, _>>()
+                })
+                .map_err(|err| io_error(&format!("list '{}'", path), err))?;
+            // Directory order is platform dependent, so sort for reproducible output
+            names.sort();
+
+            Ok(LoxValue::List(gc::list(
+                names
+                    .into_iter()
+                    .map(|name| LoxValue::String(name.into()))
+                    .collect(),
+            )))
+        }),
+    );
+}
+
+fn path_argument(
+    interpreter: &Interpreter,
+    function: &str,
+    value: &LoxValue,
+) -> Result<String, Box<dyn Error>> {
+    if !interpreter.filesystem_access() {
+        return Err(Box::new(NativeError::new(format!(
+            "Filesystem access is disabled, so '{}' can't be used",
+            function
+        ))));
+    }
+
+    Ok(string_argument(function, value)?.to_string())
+}
+
+fn io_error(action: &str, err: std::io::Error) -> Box<dyn Error> {
+    Box::new(NativeError::new(format!("Couldn't {}: {}", action, err)))
+}
@@ -0,0 +1,117 @@
+//! The `jsonParse` and `jsonStringify` natives, converting between Lox values and
+//! `json::Json`.
+
+use std::{error::Error, rc::Rc};
+
+use crate::{
+    environment::Environment,
+    gc,
+    interpreter::{LoxValue, NativeError},
+    json::{Json, MAX_DEPTH, MAX_INDENT},
+    stdlib::{index_argument, native, string_argument},
+};
+
+pub fn define(globals: &mut Environment) {
+    globals.define(
+        "jsonParse".to_string(),
+        native("jsonParse", 1..=1, |_, arguments| {
+            let text = string_argument("jsonParse", &arguments[0])?;
+            let json = Json::parse(&text).map_err(|err| NativeError::new(err.to_string()))?;
+
+            Ok(to_value(json))
+        }),
+    );
+    globals.define(
+        "jsonStringify".to_string(),
+        native("jsonStringify", 1..=2, |interpreter, arguments| {
+            let indent = match arguments.get(1) {
+                Some(value) => Some(index_argument("jsonStringify", value)?),
+                None => None,
+            };
+            if indent.is_some_and(|indent| indent > MAX_INDENT) {
+                return Err(Box::new(NativeError::new(format!(
+                    "Can't indent JSON by more than {} spaces but got {}",
+                    MAX_INDENT, arguments[1]
+                ))));
+            }
+            let json = to_json(&arguments[0], &mut Vec::new())?;
+            let text = json.try_stringify(indent, |bytes| interpreter.check_memory(bytes))?;
+            interpreter.reserve(text.len())?;
+
+            Ok(LoxValue::String(text.into()))
+        }),
+    );
+}
+
+fn to_value(json: Json) -> LoxValue {
+    match json {
+        Json::Null => LoxValue::Nil,
+        Json::Boolean(b) => LoxValue::Boolean(b),
+        Json::Number(x) => LoxValue::Number(x),
+        Json::String(s) => LoxValue::String(s.into()),
+        Json::Array(items) => LoxValue::List(gc::list(items.into_iter().map(to_value).collect())),
+        Json::Object(members) => LoxValue::Map(gc::map(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.into(), to_value(value)))
+                .collect(),
+        )),
+    }
+}
+
+/// Converts a value to JSON. `containers` holds the lists and maps currently being
+/// converted, so a value that contains itself, or nests deeper than JSON may, is
+/// reported instead of recursing forever.
+fn to_json(value: &LoxValue, containers: &mut Vec<*const ()>) -> Result<Json, Box<dyn Error>> {
+    let json = match value {
+        LoxValue::Nil => Json::Null,
+        LoxValue::Boolean(b) => Json::Boolean(*b),
+        LoxValue::Number(x) if x.is_finite() => Json::Number(*x),
+        LoxValue::Number(x) => return Err(unserializable(&x.to_string())),
+        LoxValue::String(s) => Json::String(s.to_string()),
+        LoxValue::List(list) => {
+            enter(Rc::as_ptr(list) as *const (), containers)?;
+            let items = list
+                .borrow()
+                .iter()
+                .map(|item| to_json(item, containers))
+                .collect::<Result<_, _>>()?;
+            containers.pop();
+            Json::Array(items)
+        }
+        LoxValue::Map(map) => {
+            enter(Rc::as_ptr(map) as *const (), containers)?;
+            let members = map
+                .borrow()
+                .iter()
+                .map(|(key, value)| Ok((key.to_string(), to_json(value, containers)?)))
+                .collect::<Result<_, Box<dyn Error>>>()?;
+            containers.pop();
+            Json::Object(members)
+        }
+        _ => return Err(unserializable(value.type_name())),
+    };
+
+    Ok(json)
+}
+
+fn enter(container: *const (), containers: &mut Vec<*const ()>) -> Result<(), Box<dyn Error>> {
+    if containers.contains(&container) {
+        return Err(Box::new(NativeError::new(
+            "Can't convert a value that contains itself to JSON".to_string(),
+        )));
+    }
+    if containers.len() >= MAX_DEPTH {
+        return Err(Box::new(NativeError::new(format!(
+            "Can't convert a value nested more than {} deep to JSON",
+            MAX_DEPTH
+        ))));
+    }
+    containers.push(container);
+
+    Ok(())
+}
+
+fn unserializable(what: &str) -> Box<dyn Error> {
+    Box::new(NativeError::new(format!("Can't convert {} to JSON", what)))
+}
@@ -0,0 +1,86 @@
+//! The `List` constructor and methods on list values.
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    environment::Environment,
+    gc::{self, ListItems},
+    interpreter::{LoxValue, NativeError},
+    stdlib::{index_argument, native, undefined_property, NativeResult},
+    token::{Token, TokenKind},
+};
+
+pub fn define(globals: &mut Environment) {
+    globals.define(
+        "List".to_string(),
+        native("List", 0..=usize::MAX, |_, arguments| {
+            Ok(LoxValue::List(gc::list(arguments)))
+        }),
+    );
+}
+
+/// Looks up a method on a list, bound to that list.
+pub fn method(name: &Token, list: Rc<RefCell<ListItems>>) -> NativeResult {
+    let id = match name.kind() {
+        TokenKind::Identifier(id) => &**id,
+        _ => "",
+    };
+
+    let method = match id {
+        "len" => native("len", 0..=0, move |_, _| {
+            Ok(LoxValue::Number(list.borrow().len() as f64))
+        }),
+        "get" => native("get", 1..=1, move |_, arguments| {
+            get(&list, index_argument("get", &arguments[0])?)
+        }),
+        "set" => native("set", 2..=2, move |_, arguments| {
+            set(
+                &list,
+                index_argument("set", &arguments[0])?,
+                arguments[1].clone(),
+            )
+        }),
+        "push" => native("push", 1..=1, move |_, arguments| {
+            gc::charge(gc::value_size(&arguments[0]));
+            list.borrow_mut().push(arguments[0].clone());
+            Ok(LoxValue::Nil)
+        }),
+        "pop" => native("pop", 0..=0, move |_, _| match list.borrow_mut().pop() {
+            Some(value) => Ok(value),
+            None => Err(Box::new(NativeError::new(
+                "Can't pop from an empty list".to_string(),
+            ))),
+        }),
+        _ => return Err(undefined_property(name, "list")),
+    };
+
+    Ok(method)
+}
+
+/// Reads an item, as done by both `list.get(i)` and `list[i]`.
+pub fn get(list: &RefCell<ListItems>, index: usize) -> NativeResult {
+    match list.borrow().get(index) {
+        Some(value) => Ok(value.clone()),
+        None => Err(out_of_range(index, list.borrow().len())),
+    }
+}
+
+/// Replaces an item, as done by both `list.set(i, value)` and `list[i] = value`.
+pub fn set(list: &RefCell<ListItems>, index: usize, value: LoxValue) -> NativeResult {
+    let len = list.borrow().len();
+    match list.borrow_mut().get_mut(index) {
+        Some(item) => {
+            gc::charge(gc::value_size(&value));
+            *item = value.clone();
+            Ok(value)
+        }
+        None => Err(out_of_range(index, len)),
+    }
+}
+
+fn out_of_range(index: usize, len: usize) -> Box<dyn std::error::Error> {
+    Box::new(NativeError::new(format!(
+        "List index {} out of range for length {}",
+        index, len
+    )))
+}
@@ -0,0 +1,70 @@
+//! The `Map` constructor and methods on map values.
+
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+use crate::{
+    environment::Environment,
+    gc::{self, MapEntries},
+    interpreter::LoxValue,
+    stdlib::{native, string_argument, undefined_property, NativeResult},
+    token::{Token, TokenKind},
+};
+
+pub fn define(globals: &mut Environment) {
+    globals.define(
+        "Map".to_string(),
+        native("Map", 0..=0, |_, _| {
+            Ok(LoxValue::Map(gc::map(BTreeMap::new())))
+        }),
+    );
+}
+
+/// Looks up a method on a map, bound to that map.
+pub fn method(name: &Token, map: Rc<RefCell<MapEntries>>) -> NativeResult {
+    let id = match name.kind() {
+        TokenKind::Identifier(id) => &**id,
+        _ => "",
+    };
+
+    let method = match id {
+        "len" => native("len", 0..=0, move |_, _| {
+            Ok(LoxValue::Number(map.borrow().len() as f64))
+        }),
+        // Missing keys read as nil, like undefined fields would in other languages
+        "get" => native("get", 1..=1, move |_, arguments| {
+            let key = string_argument("get", &arguments[0])?;
+            Ok(map.borrow().get(&key).cloned().unwrap_or(LoxValue::Nil))
+        }),
+        "set" => native("set", 2..=2, move |_, arguments| {
+            let key = string_argument("set", &arguments[0])?;
+            Ok(set(&map, key, arguments[1].clone()))
+        }),
+        "has" => native("has", 1..=1, move |_, arguments| {
+            let key = string_argument("has", &arguments[0])?;
+            Ok(LoxValue::Boolean(map.borrow().contains_key(&key)))
+        }),
+        "remove" => native("remove", 1..=1, move |_, arguments| {
+            let key = string_argument("remove", &arguments[0])?;
+            Ok(map.borrow_mut().remove(&key).unwrap_or(LoxValue::Nil))
+        }),
+        "keys" => native("keys", 0..=0, move |_, _| {
+            let keys = map.borrow().keys().cloned().map(LoxValue::String).collect();
+            Ok(LoxValue::List(gc::list(keys)))
+        }),
+        "values" => native("values", 0..=0, move |_, _| {
+            let values = map.borrow().values().cloned().collect();
+            Ok(LoxValue::List(gc::list(values)))
+        }),
+        _ => return Err(undefined_property(name, "map")),
+    };
+
+    Ok(method)
+}
+
+/// Adds or replaces an entry, as done by both `map.set(key, value)` and
+/// `map[key] = value`.
+pub fn set(map: &RefCell<MapEntries>, key: Rc<str>, value: LoxValue) -> LoxValue {
+    gc::charge(gc::entries_size([(&key, &value)]));
+    map.borrow_mut().insert(key, value.clone());
+    value
+}
@@ -0,0 +1,150 @@
+//! The `math` namespace: numeric functions, constants and a seedable random number
+//! generator.
+
+use std::{
+    f64::consts::{E, PI},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    environment::Environment,
+    interpreter::LoxValue,
+    stdlib::{namespace, native, number_argument},
+};
+
+pub fn define(globals: &mut Environment) {
+    let members = vec![
+        ("PI", LoxValue::Number(PI)),
+        ("E", LoxValue::Number(E)),
+        ("INFINITY", LoxValue::Number(f64::INFINITY)),
+        ("NAN", LoxValue::Number(f64::NAN)),
+        ("sqrt", unary("sqrt", f64::sqrt)),
+        ("abs", unary("abs", f64::abs)),
+        ("floor", unary("floor", f64::floor)),
+        ("ceil", unary("ceil", f64::ceil)),
+        ("round", unary("round", f64::round)),
+        ("trunc", unary("trunc", f64::trunc)),
+        ("sin", unary("sin", f64::sin)),
+        ("cos", unary("cos", f64::cos)),
+        ("tan", unary("tan", f64::tan)),
+        ("asin", unary("asin", f64::asin)),
+        ("acos", unary("acos", f64::acos)),
+        ("atan", unary("atan", f64::atan)),
+        ("exp", unary("exp", f64::exp)),
+        ("log", unary("log", f64::ln)),
+        ("log2", unary("log2", f64::log2)),
+        ("log10", unary("log10", f64::log10)),
+        ("sign", unary("sign", sign)),
+        ("pow", binary("pow", f64::powf)),
+        ("atan2", binary("atan2", f64::atan2)),
+        (
+            "min",
+            native("min", 1..=usize::MAX, |_, arguments| {
+                fold("min", arguments, f64::min)
+            }),
+        ),
+        (
+            "max",
+            native("max", 1..=usize::MAX, |_, arguments| {
+                fold("max", arguments, f64::max)
+            }),
+        ),
+        (
+            "random",
+            native("random", 0..=0, |interpreter, _| {
+                Ok(LoxValue::Number(interpreter.random().next_f64()))
+            }),
+        ),
+        (
+            "seed",
+            native("seed", 1..=1, |interpreter, arguments| {
+                let seed = number_argument("seed", &arguments[0])?;
+                *interpreter.random() = Random::new(seed.to_bits());
+                Ok(LoxValue::Nil)
+            }),
+        ),
+    ];
+
+    globals.define("math".to_string(), namespace("math", members));
+}
+
+fn unary(name: &'static str, function: fn(f64) -> f64) -> LoxValue {
+    native(name, 1..=1, move |_, arguments| {
+        Ok(LoxValue::Number(function(number_argument(
+            name,
+            &arguments[0],
+        )?)))
+    })
+}
+
+fn binary(name: &'static str, function: fn(f64, f64) -> f64) -> LoxValue {
+    native(name, 2..=2, move |_, arguments| {
+        let x = number_argument(name, &arguments[0])?;
+        let y = number_argument(name, &arguments[1])?;
+
+        Ok(LoxValue::Number(function(x, y)))
+    })
+}
+
+fn fold(
+    name: &str,
+    arguments: Vec<LoxValue>,
+    function: fn(f64, f64) -> f64,
+) -> Result<LoxValue, Box<dyn std::error::Error>> {
+    let mut result = number_argument(name, &arguments[0])?;
+    for argument in &arguments[1..] {
+        result = function(result, number_argument(name, argument)?);
+    }
+
+    Ok(LoxValue::Number(result))
+}
+
+fn sign(x: f64) -> f64 {
+    if x == 0.0 || x.is_nan() {
+        x
+    } else {
+        x.signum()
+    }
+}
+
+/// A xorshift64* generator. Not suitable for cryptography, but fast and fully
+/// reproducible from its seed.
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        // Mix the seed with splitmix64 so that similar seeds give unrelated sequences
+        // and the state is never zero
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        Self {
+            state: if z == 0 { 1 } else { z },
+        }
+    }
+
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+
+        Self::new(nanos as u64)
+    }
+
+    /// Returns a number in the range [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let value = self.state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+
+        // The top 53 bits fill an f64 mantissa exactly
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,47 @@
+//! Natives for inspecting and driving the cycle collector and the memory estimate.
+
+use std::{collections::BTreeMap, rc::Rc};
+
+use crate::{environment::Environment, gc, interpreter::LoxValue, stdlib::native};
+
+pub fn define(globals: &mut Environment) {
+    // Returns what this collection freed, by kind
+    globals.define(
+        "gc".to_string(),
+        native("gc", 0..=0, |_, _| {
+            let collection = gc::collect();
+            Ok(counts(&[
+                ("environments", collection.environments),
+                ("instances", collection.instances),
+                ("lists", collection.lists),
+                ("maps", collection.maps),
+                ("classes", collection.classes),
+                ("traits", collection.traits),
+                ("collected", collection.collected()),
+                ("live", collection.live),
+                ("bytes", collection.bytes),
+            ]))
+        }),
+    );
+    globals.define(
+        "gcStats".to_string(),
+        native("gcStats", 0..=0, |_, _| {
+            let stats = gc::stats();
+            Ok(counts(&[
+                ("collections", stats.collections),
+                ("collected", stats.collected),
+                ("tracked", gc::tracked()),
+                ("bytes", gc::usage()),
+            ]))
+        }),
+    );
+}
+
+fn counts(entries: &[(&str, usize)]) -> LoxValue {
+    LoxValue::Map(gc::map(
+        entries
+            .iter()
+            .map(|(name, count)| (Rc::from(*name), LoxValue::Number(*count as f64)))
+            .collect::<BTreeMap<Rc<str>, LoxValue>>(),
+    ))
+}
@@ -0,0 +1,177 @@
+//! Native functions and methods that make up the Lox standard library. Each
+//! submodule is one group of related natives.
+
+use std::{collections::HashMap, error::Error, ops::RangeInclusive, rc::Rc};
+
+use crate::{
+    environment::Environment,
+    gc,
+    intern::intern,
+    interpreter::{
+        Class, Instance, Interpreter, LoxValue, NativeError, NativeFunction, RuntimeError,
+    },
+    token::{Token, TokenKind},
+};
+
+pub mod io;
+pub mod json;
+pub mod list;
+pub mod map;
+pub mod math;
+pub mod memory;
+pub mod process;
+pub mod reflect;
+pub mod string;
+pub mod time;
+
+/// A group of natives that an embedder can make available to scripts. Groups that
+/// reach outside the interpreter are `Time`, `Io` and `Process`; the rest only work
+/// on Lox values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// `clock`.
+    Time,
+    /// The `math` namespace.
+    Math,
+    /// `str`, `num` and `type`.
+    String,
+    /// The `List` and `Map` constructors.
+    Collections,
+    /// `jsonParse` and `jsonStringify`.
+    Json,
+    /// Class and instance inspection, such as `classOf` and `getField`.
+    Reflect,
+    /// `gc` and `gcStats`.
+    Memory,
+    /// Standard input and the file natives.
+    Io,
+    /// `args`, `getenv` and `exit`.
+    Process,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 9] = [
+        Capability::Time,
+        Capability::Math,
+        Capability::String,
+        Capability::Collections,
+        Capability::Json,
+        Capability::Reflect,
+        Capability::Memory,
+        Capability::Io,
+        Capability::Process,
+    ];
+
+    /// Looks up a group by the name used on the command line.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|capability| capability.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Capability::Time => "time",
+            Capability::Math => "math",
+            Capability::String => "string",
+            Capability::Collections => "collections",
+            Capability::Json => "json",
+            Capability::Reflect => "reflect",
+            Capability::Memory => "memory",
+            Capability::Io => "io",
+            Capability::Process => "process",
+        }
+    }
+
+    /// Defines the group's natives in `globals`.
+    pub fn define(&self, globals: &mut Environment) {
+        match self {
+            Capability::Time => time::define(globals),
+            Capability::Math => math::define(globals),
+            Capability::String => string::define(globals),
+            Capability::Collections => {
+                list::define(globals);
+                map::define(globals);
+            }
+            Capability::Json => json::define(globals),
+            Capability::Reflect => reflect::define(globals),
+            Capability::Memory => memory::define(globals),
+            Capability::Io => io::define(globals),
+            Capability::Process => process::define(globals),
+        }
+    }
+}
+
+pub type NativeResult = Result<LoxValue, Box<dyn Error>>;
+
+pub fn native(
+    name: &str,
+    arity: RangeInclusive<usize>,
+    function: impl Fn(&mut Interpreter, Vec<LoxValue>) -> NativeResult + 'static,
+) -> LoxValue {
+    LoxValue::NativeFunction(NativeFunction::new(intern(name), arity, Rc::new(function)))
+}
+
+/// Groups natives under a single global, so they are accessed as `name.member`. The
+/// members are read-only, so scripts can't break the library by replacing them.
+pub fn namespace(name: &str, members: Vec<(&str, LoxValue)>) -> LoxValue {
+    let class = gc::class(Class::new(
+        name.into(),
+        None,
+        HashMap::new(),
+        HashMap::new(),
+    ));
+    let fields = members
+        .into_iter()
+        .map(|(member, value)| (member.into(), value))
+        .collect();
+
+    LoxValue::Instance(gc::instance(Instance::read_only(class, fields)))
+}
+
+pub fn number_argument(function: &str, value: &LoxValue) -> Result<f64, Box<dyn Error>> {
+    match value {
+        LoxValue::Number(x) => Ok(*x),
+        _ => Err(Box::new(NativeError::new(format!(
+            "Expected number argument to '{}' but got {}",
+            function,
+            value.type_name()
+        )))),
+    }
+}
+
+pub fn string_argument(function: &str, value: &LoxValue) -> Result<Rc<str>, Box<dyn Error>> {
+    match value {
+        LoxValue::String(s) => Ok(s.clone()),
+        _ => Err(Box::new(NativeError::new(format!(
+            "Expected string argument to '{}' but got {}",
+            function,
+            value.type_name()
+        )))),
+    }
+}
+
+/// Reads a non-negative whole number, as used for string and list indices.
+pub fn index_argument(function: &str, value: &LoxValue) -> Result<usize, Box<dyn Error>> {
+    let x = number_argument(function, value)?;
+    if x < 0.0 || x.fract() != 0.0 {
+        return Err(Box::new(NativeError::new(format!(
+            "Expected non-negative integer argument to '{}' but got {}",
+            function, value
+        ))));
+    }
+
+    Ok(x as usize)
+}
+
+fn undefined_property(name: &Token, type_name: &str) -> Box<dyn Error> {
+    let id = match name.kind() {
+        TokenKind::Identifier(id) => &**id,
+        _ => "",
+    };
+
+    Box::new(RuntimeError::new(
+        name.clone(),
+        format!("Undefined property '{}' on {}", id, type_name),
+    ))
+}
@@ -0,0 +1,50 @@
+//! Natives for interacting with the surrounding process: the `args` list,
+//! environment variables and the exit code.
+
+use crate::{
+    environment::Environment,
+    gc,
+    interpreter::{ExitError, LoxValue, NativeError},
+    stdlib::{native, number_argument, string_argument},
+};
+
+pub fn define(globals: &mut Environment) {
+    globals.define("args".to_string(), args(Vec::new()));
+    globals.define(
+        "getenv".to_string(),
+        native("getenv", 1..=1, |_, arguments| {
+            let name = string_argument("getenv", &arguments[0])?;
+
+            Ok(match std::env::var(&*name) {
+                Ok(value) => LoxValue::String(value.into()),
+                Err(_) => LoxValue::Nil,
+            })
+        }),
+    );
+    globals.define(
+        "exit".to_string(),
+        native("exit", 0..=1, |_, arguments| {
+            let code = match arguments.first() {
+                Some(value) => number_argument("exit", value)?,
+                None => 0.0,
+            };
+            if !(0.0..=255.0).contains(&code) || code.fract() != 0.0 {
+                return Err(Box::new(NativeError::new(format!(
+                    "Expected exit code between 0 and 255 but got {}",
+                    code
+                ))));
+            }
+
+            Err(Box::new(ExitError::new(code as u8)))
+        }),
+    );
+}
+
+/// Builds the value of the `args` global from the script's command-line arguments.
+pub fn args(args: Vec<String>) -> LoxValue {
+    LoxValue::List(gc::list(
+        args.into_iter()
+            .map(|arg| LoxValue::String(arg.into()))
+            .collect(),
+    ))
+}
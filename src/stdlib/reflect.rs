@@ -0,0 +1,153 @@
+//! Natives for inspecting classes and instances at runtime.
+
+use std::{cell::RefCell, error::Error, rc::Rc};
+
+use crate::{
+    environment::Environment,
+    gc,
+    interpreter::{Class, Instance, LoxValue, NativeError},
+    stdlib::{native, string_argument},
+};
+
+pub fn define(globals: &mut Environment) {
+    globals.define(
+        "classOf".to_string(),
+        native("classOf", 1..=1, |_, arguments| {
+            let instance = instance_argument("classOf", &arguments[0])?;
+            let class = instance.borrow().class().clone();
+
+            Ok(LoxValue::Class(class))
+        }),
+    );
+    globals.define(
+        "className".to_string(),
+        native("className", 1..=1, |_, arguments| {
+            let class = class_argument("className", &arguments[0])?;
+
+            Ok(LoxValue::String(class.name().into()))
+        }),
+    );
+    globals.define(
+        "superclassOf".to_string(),
+        native("superclassOf", 1..=1, |_, arguments| {
+            let class = class_argument("superclassOf", &arguments[0])?;
+
+            Ok(match class.superclass() {
+                Some(superclass) => LoxValue::Class(superclass.clone()),
+                None => LoxValue::Nil,
+            })
+        }),
+    );
+    globals.define(
+        "fields".to_string(),
+        native("fields", 1..=1, |_, arguments| {
+            let instance = instance_argument("fields", &arguments[0])?;
+            let mut names: Vec<String> = instance
+                .borrow()
+                .fields()
+                .keys()
+                .map(|name| name.to_string())
+                .collect();
+            names.sort();
+
+            Ok(string_list(names))
+        }),
+    );
+    globals.define(
+        "hasField".to_string(),
+        native("hasField", 2..=2, |_, arguments| {
+            let instance = instance_argument("hasField", &arguments[0])?;
+            let name = string_argument("hasField", &arguments[1])?;
+            let has_field = instance.borrow().fields().contains_key(&name);
+
+            Ok(LoxValue::Boolean(has_field))
+        }),
+    );
+    globals.define(
+        "getField".to_string(),
+        native("getField", 2..=2, |_, arguments| {
+            let instance = instance_argument("getField", &arguments[0])?;
+            let name = string_argument("getField", &arguments[1])?;
+            let value = instance.borrow().fields().get(&name).cloned();
+
+            value.ok_or_else(|| {
+                Box::new(NativeError::new(format!("Undefined field '{}'", name))) as Box<dyn Error>
+            })
+        }),
+    );
+    globals.define(
+        "setField".to_string(),
+        native("setField", 3..=3, |_, arguments| {
+            let instance = instance_argument("setField", &arguments[0])?;
+            let name = string_argument("setField", &arguments[1])?;
+            if instance.borrow().is_read_only() {
+                return Err(Box::new(NativeError::new(format!(
+                    "Can't set property '{}' of read-only '{}'",
+                    name,
+                    instance.borrow().class().name()
+                ))));
+            }
+            gc::charge(gc::entries_size([(&name, &arguments[2])]));
+            instance
+                .borrow_mut()
+                .fields_mut()
+                .insert(name, arguments[2].clone());
+
+            Ok(arguments[2].clone())
+        }),
+    );
+    globals.define(
+        "methods".to_string(),
+        native("methods", 1..=1, |_, arguments| {
+            let class = class_argument("methods", &arguments[0])?;
+
+            Ok(string_list(class.method_names()))
+        }),
+    );
+    globals.define(
+        "instanceOf".to_string(),
+        native("instanceOf", 2..=2, |_, arguments| {
+            let class = class_argument("instanceOf", &arguments[1])?;
+            let is_instance = match &arguments[0] {
+                LoxValue::Instance(instance) => instance.borrow().class().is_subclass_of(&class),
+                _ => false,
+            };
+
+            Ok(LoxValue::Boolean(is_instance))
+        }),
+    );
+}
+
+fn instance_argument(
+    function: &str,
+    value: &LoxValue,
+) -> Result<Rc<RefCell<Instance>>, Box<dyn Error>> {
+    match value {
+        LoxValue::Instance(instance) => Ok(instance.clone()),
+        _ => Err(Box::new(NativeError::new(format!(
+            "Expected instance argument to '{}' but got {}",
+            function,
+            value.type_name()
+        )))),
+    }
+}
+
+fn class_argument(function: &str, value: &LoxValue) -> Result<Rc<Class>, Box<dyn Error>> {
+    match value {
+        LoxValue::Class(class) => Ok(class.clone()),
+        _ => Err(Box::new(NativeError::new(format!(
+            "Expected class argument to '{}' but got {}",
+            function,
+            value.type_name()
+        )))),
+    }
+}
+
+fn string_list(strings: Vec<String>) -> LoxValue {
+    LoxValue::List(gc::list(
+        strings
+            .into_iter()
+            .map(|s| LoxValue::String(s.into()))
+            .collect(),
+    ))
+}
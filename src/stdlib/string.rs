@@ -0,0 +1,161 @@
+//! Methods on string values and the `str`, `num` and `type` conversion natives.
+
+use std::{mem::size_of, rc::Rc};
+
+use crate::{
+    environment::Environment,
+    gc,
+    interpreter::{LoxValue, NativeError},
+    stdlib::{index_argument, native, string_argument, undefined_property, NativeResult},
+    token::{Token, TokenKind},
+};
+
+pub fn define(globals: &mut Environment) {
+    globals.define(
+        "str".to_string(),
+        native("str", 1..=1, |interpreter, arguments| {
+            let string = interpreter.stringify(arguments[0].clone())?;
+            interpreter.reserve(string.len())?;
+
+            Ok(LoxValue::String(string.into()))
+        }),
+    );
+    globals.define(
+        "num".to_string(),
+        native("num", 1..=1, |_, arguments| match &arguments[0] {
+            LoxValue::Number(x) => Ok(LoxValue::Number(*x)),
+            // Strings that don't hold a number convert to nil
+            LoxValue::String(s) => Ok(match s.trim().parse::<f64>() {
+                Ok(x) => LoxValue::Number(x),
+                Err(_) => LoxValue::Nil,
+            }),
+            value => Err(Box::new(NativeError::new(format!(
+                "Can't convert {} to a number",
+                value.type_name()
+            )))),
+        }),
+    );
+    globals.define(
+        "type".to_string(),
+        native("type", 1..=1, |_, arguments| {
+            Ok(LoxValue::String(arguments[0].type_name().into()))
+        }),
+    );
+}
+
+/// Reads the character at a character index, as done by `string[i]`.
+pub fn char_at(string: &str, index: usize) -> NativeResult {
+    match string.chars().nth(index) {
+        Some(c) => Ok(LoxValue::String(c.to_string().into())),
+        None => Err(Box::new(NativeError::new(format!(
+            "String index {} out of range for length {}",
+            index,
+            string.chars().count()
+        )))),
+    }
+}
+
+/// Looks up a method on a string, bound to that string. Indices count characters,
+/// not bytes.
+pub fn method(name: &Token, string: Rc<str>) -> NativeResult {
+    let id = match name.kind() {
+        TokenKind::Identifier(id) => &**id,
+        _ => "",
+    };
+
+    let method = match id {
+        "len" => native("len", 0..=0, move |_, _| {
+            Ok(LoxValue::Number(string.chars().count() as f64))
+        }),
+        "substr" => native("substr", 1..=2, move |interpreter, arguments| {
+            let chars: Vec<char> = string.chars().collect();
+            let start = index_argument("substr", &arguments[0])?;
+            if start > chars.len() {
+                return Err(Box::new(NativeError::new(format!(
+                    "String index {} out of range for length {}",
+                    start,
+                    chars.len()
+                ))));
+            }
+            let end = match arguments.get(1) {
+                Some(length) => start
+                    .saturating_add(index_argument("substr", length)?)
+                    .min(chars.len()),
+                None => chars.len(),
+            };
+            interpreter.reserve(chars[start..end].iter().map(|c| c.len_utf8()).sum())?;
+
+            Ok(LoxValue::String(
+                chars[start..end].iter().collect::<String>().into(),
+            ))
+        }),
+        "indexOf" => native("indexOf", 1..=1, move |_, arguments| {
+            let needle = string_argument("indexOf", &arguments[0])?;
+            let index = match string.find(&*needle) {
+                Some(byte_index) => string[..byte_index].chars().count() as f64,
+                None => -1.0,
+            };
+
+            Ok(LoxValue::Number(index))
+        }),
+        "split" => native("split", 1..=1, move |interpreter, arguments| {
+            let separator = string_argument("split", &arguments[0])?;
+            let count = if separator.is_empty() {
+                string.chars().count()
+            } else {
+                string.matches(&*separator).count() + 1
+            };
+            interpreter.reserve(string.len() + count * size_of::<LoxValue>())?;
+
+            // An empty separator splits the string into its characters
+            let parts: Vec<LoxValue> = if separator.is_empty() {
+                string
+                    .chars()
+                    .map(|c| LoxValue::String(c.to_string().into()))
+                    .collect()
+            } else {
+                string
+                    .split(&*separator)
+                    .map(|part| LoxValue::String(part.into()))
+                    .collect()
+            };
+
+            Ok(LoxValue::List(gc::list(parts)))
+        }),
+        "upper" => native("upper", 0..=0, move |interpreter, _| {
+            interpreter.reserve(string.len())?;
+
+            Ok(LoxValue::String(string.to_uppercase().into()))
+        }),
+        "lower" => native("lower", 0..=0, move |interpreter, _| {
+            interpreter.reserve(string.len())?;
+
+            Ok(LoxValue::String(string.to_lowercase().into()))
+        }),
+        "trim" => native("trim", 0..=0, move |interpreter, _| {
+            let trimmed = string.trim();
+            interpreter.reserve(trimmed.len())?;
+
+            Ok(LoxValue::String(trimmed.into()))
+        }),
+        "replace" => native("replace", 2..=2, move |interpreter, arguments| {
+            let from = string_argument("replace", &arguments[0])?;
+            let to = string_argument("replace", &arguments[1])?;
+            // Every match is swapped for the replacement, which can grow the string a lot
+            let count = string.matches(&*from).count();
+            interpreter.reserve(
+                (string.len() - count * from.len()).saturating_add(count.saturating_mul(to.len())),
+            )?;
+
+            Ok(LoxValue::String(string.replace(&*from, &to).into()))
+        }),
+        "startsWith" => native("startsWith", 1..=1, move |_, arguments| {
+            let prefix = string_argument("startsWith", &arguments[0])?;
+
+            Ok(LoxValue::Boolean(string.starts_with(&*prefix)))
+        }),
+        _ => return Err(undefined_property(name, "string")),
+    };
+
+    Ok(method)
+}
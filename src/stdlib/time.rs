@@ -0,0 +1,58 @@
+//! Natives for reading the time, and the clocks they read from.
+
+use std::{
+    cell::Cell,
+    rc::Rc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{environment::Environment, interpreter::LoxValue, stdlib::native};
+
+pub fn define(globals: &mut Environment) {
+    globals.define(
+        "clock".to_string(),
+        native("clock", 0..=0, |interpreter, _| {
+            Ok(LoxValue::Number(interpreter.clock().now().as_secs_f64()))
+        }),
+    );
+}
+
+/// Where `clock` gets the time from.
+#[derive(Debug, Default, Clone)]
+pub enum Clock {
+    /// The time since the Unix epoch.
+    #[default]
+    System,
+    /// A time controlled by the host, for reproducible runs.
+    Virtual(VirtualClock),
+}
+
+impl Clock {
+    pub fn now(&self) -> Duration {
+        match self {
+            Clock::System => SystemTime::now().duration_since(UNIX_EPOCH).unwrap(),
+            Clock::Virtual(clock) => clock.now(),
+        }
+    }
+}
+
+/// A clock that starts at zero and only moves when the host moves it. Clones share
+/// the same time, so the host keeps one to control the interpreter's clock.
+#[derive(Debug, Default, Clone)]
+pub struct VirtualClock {
+    now: Rc<Cell<Duration>>,
+}
+
+impl VirtualClock {
+    pub fn now(&self) -> Duration {
+        self.now.get()
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.now.set(now);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+}
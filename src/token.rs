@@ -1,16 +1,21 @@
-use std::{fmt::Display, hash::Hash};
+use std::{fmt::Display, hash::Hash, rc::Rc};
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
 pub struct Token {
     kind: TokenKind,
     line: usize,
+    // Column of the token's first character, or 0 for tokens made up by the parser or
+    // interpreter. Part of the token's identity, so two uses of the same name on one
+    // line are still different expressions to the resolver.
+    column: usize,
 }
 
 impl Token {
     pub fn new(kind: TokenKind, line: usize) -> Self {
         Self {
-            kind: kind,
-            line: line,
+            kind,
+            line,
+            column: 0,
         }
     }
 
@@ -21,6 +26,16 @@ impl Token {
     pub fn line(&self) -> &usize {
         &self.line
     }
+
+    /// The token's first character, counting from 1, or 0 if the token isn't from
+    /// the source.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn set_column(&mut self, column: usize) {
+        self.column = column;
+    }
 }
 
 impl Display for Token {
@@ -36,6 +51,8 @@ pub enum TokenKind {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
@@ -43,6 +60,9 @@ pub enum TokenKind {
     Semicolon,
     Slash,
     Star,
+    Percent,
+    Question,
+    Colon,
 
     // One or two character tokens
     Bang,
@@ -53,17 +73,28 @@ pub enum TokenKind {
     GreaterEqual,
     Less,
     LessEqual,
+    StarStar,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PlusPlus,
+    MinusMinus,
 
     // Literals
-    Identifier(String),
-    String(String),
+    Identifier(Rc<str>),
+    String(Rc<str>),
+    // The part of a string literal before a "${", which starts an interpolated expression
+    StringInterpolation(String),
     Number(f64),
 
     // Keywords
     And,
+    Catch,
     Class,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
@@ -73,9 +104,13 @@ pub enum TokenKind {
     Return,
     Super,
     This,
+    Throw,
+    Trait,
     True,
+    Try,
     Var,
     While,
+    With,
 
     Eof,
 }
@@ -97,7 +132,9 @@ impl Display for TokenKind {
             Self::And => "and",
             Self::Bang => "!",
             Self::BangEqual => "!=",
+            Self::Catch => "catch",
             Self::Class => "class",
+            Self::Colon => ":",
             Self::Comma => ",",
             Self::Dot => ".",
             Self::Else => "else",
@@ -105,6 +142,7 @@ impl Display for TokenKind {
             Self::Equal => "=",
             Self::EqualEqual => "==",
             Self::False => "false",
+            Self::Finally => "finally",
             Self::For => "for",
             Self::Fun => "fun",
             Self::Greater => ">",
@@ -112,27 +150,43 @@ impl Display for TokenKind {
             Self::Identifier(id) => &format!("Identifier:{}", id),
             Self::If => "if",
             Self::LeftBrace => "{",
+            Self::LeftBracket => "[",
             Self::LeftParen => "(",
             Self::Less => "<",
             Self::LessEqual => "<=",
             Self::Minus => "-",
+            Self::MinusEqual => "-=",
+            Self::MinusMinus => "--",
             Self::Nil => "nil",
             Self::Number(x) => &format!("Number:{}", x),
             Self::Or => "or",
+            Self::Percent => "%",
             Self::Plus => "+",
+            Self::PlusEqual => "+=",
+            Self::PlusPlus => "++",
             Self::Print => "print",
+            Self::Question => "?",
             Self::Return => "return",
             Self::RightBrace => "}",
+            Self::RightBracket => "]",
             Self::RightParen => ")",
             Self::Semicolon => ";",
             Self::Slash => "/",
+            Self::SlashEqual => "/=",
             Self::Star => "*",
+            Self::StarEqual => "*=",
+            Self::StarStar => "**",
             Self::String(str) => &format!("\"{}\"", str),
+            Self::StringInterpolation(str) => &format!("\"{}${{", str),
             Self::Super => "super",
             Self::This => "this",
+            Self::Throw => "throw",
+            Self::Trait => "trait",
             Self::True => "true",
+            Self::Try => "try",
             Self::Var => "var",
             Self::While => "while",
+            Self::With => "with",
         };
 
         write!(f, "{}", token_string)